@@ -1,5 +1,13 @@
 use std::ops::{Add, Mul};
 
+use crate::direction::Direction;
+
+#[cfg(feature = "render")]
+use cgmath::{Vector2, Vector4, Zero};
+
+#[cfg(feature = "render")]
+use gfx::{Anchor, Batch, TextureArray, Xform2D};
+
 /// Tweenable object trait
 /// Used for smooth movement/animation from an initial state to a final state over a defined period of time.
 pub trait Tween<T> {
@@ -157,3 +165,390 @@ impl Add<Frame> for Frame {
         Self(self.0 + rhs.0)
     }
 }
+
+/// Gameplay event tagged to a frame of an animation
+/// Sent when the animation reaches the frame, so actions line up with what's drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnimEvent {
+    /// Play the animation's sound effect
+    Sound,
+    /// A foot touches the ground
+    Footstep,
+    /// An attack connects
+    Hit,
+}
+
+/// Frame-stepped animation sequence, with events tagged to frames
+/// Unlike the tweens, every frame is visited, so no tagged event is skipped on a long update
+#[derive(Debug, Clone)]
+pub struct AnimSequence {
+    frame_count: usize,
+    // Time each frame is shown for, in seconds
+    frame_time: f64,
+    looping: bool,
+    // Events and the frame they're sent on
+    events: Vec<(usize, AnimEvent)>,
+    // Current frame, and the time it's been shown for
+    frame: usize,
+    elapsed: f64,
+    // Set once the first frame's events have been sent
+    started: bool,
+    done: bool,
+}
+
+impl AnimSequence {
+    /// Create a sequence of `frame_count` frames, each shown for `frame_time` seconds
+    pub fn new(frame_count: usize, frame_time: f64, looping: bool) -> Self {
+        assert!(
+            frame_count > 0,
+            "Animation sequences need at least one frame"
+        );
+        assert!(
+            frame_time > 0.0,
+            "Animation frames need a positive duration"
+        );
+        Self {
+            frame_count,
+            frame_time,
+            looping,
+            events: Vec::new(),
+            frame: 0,
+            elapsed: 0.0,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Tag a frame with an event
+    pub fn with_event(mut self, frame: usize, event: AnimEvent) -> Self {
+        assert!(frame < self.frame_count, "Event frame is out of range");
+        self.events.push((frame, event));
+        self
+    }
+
+    /// Get the current frame
+    pub fn frame(&self) -> Frame {
+        Frame(self.frame)
+    }
+
+    /// Check if a non-looping sequence has finished its last frame
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Reset the sequence to its first frame
+    /// The first frame's events are sent again on the next update
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.started = false;
+        self.done = false;
+    }
+
+    /// Update the sequence with a delta time, sending the events of every frame entered
+    /// Returns the current frame
+    pub fn update<F>(&mut self, delta: f64, mut emit: F) -> Frame
+    where
+        F: FnMut(AnimEvent),
+    {
+        if !self.started {
+            self.started = true;
+            self.emit_frame(&mut emit);
+        }
+        if !self.done {
+            self.elapsed += delta;
+            while self.elapsed >= self.frame_time {
+                if self.frame + 1 < self.frame_count {
+                    self.frame += 1;
+                } else if self.looping {
+                    self.frame = 0;
+                } else {
+                    // Hold the last frame
+                    self.elapsed = 0.0;
+                    self.done = true;
+                    break;
+                }
+                self.elapsed -= self.frame_time;
+                self.emit_frame(&mut emit);
+            }
+        }
+        self.frame()
+    }
+
+    fn emit_frame<F>(&self, emit: &mut F)
+    where
+        F: FnMut(AnimEvent),
+    {
+        for (_, event) in self.events.iter().filter(|(frame, _)| *frame == self.frame) {
+            emit(*event);
+        }
+    }
+}
+
+/// Animation state of an entity
+/// Each state is drawn from its own CL2 file, with a group per direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum AnimState {
+    #[default]
+    Idle,
+    Walk,
+    Attack,
+    Hit,
+    Death,
+}
+
+impl AnimState {
+    pub const COUNT: usize = 5;
+    pub const ALL: [AnimState; AnimState::COUNT] = [
+        AnimState::Idle,
+        AnimState::Walk,
+        AnimState::Attack,
+        AnimState::Hit,
+        AnimState::Death,
+    ];
+
+    /// Check if the state can be replaced by another one
+    /// Idle and walking give way to anything, attacks to being hit or dying, hits only to dying,
+    /// and death is terminal
+    fn can_change_to(&self, next: AnimState) -> bool {
+        match self {
+            AnimState::Idle | AnimState::Walk => true,
+            AnimState::Attack => matches!(next, AnimState::Hit | AnimState::Death),
+            AnimState::Hit => next == AnimState::Death,
+            AnimState::Death => false,
+        }
+    }
+}
+
+/// What to draw for an entity: the state picks the sprite file, the direction its group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimPose {
+    pub state: AnimState,
+    pub direction: Direction,
+    pub frame: usize,
+}
+
+/// Per-entity animation controller
+/// Glue between world state and sprite drawing: entities request states as they act, and the
+/// controller applies the transition rules and steps the sequence of the current state.
+/// Attacks and hits return to idle once their sequence is done, so those should be one-shot.
+#[derive(Debug, Clone)]
+pub struct AnimController {
+    // Sequence of every state, indexed by state
+    sequences: [AnimSequence; AnimState::COUNT],
+    state: AnimState,
+    direction: Direction,
+}
+
+impl AnimController {
+    /// Create a controller from the sequence of every state, in `AnimState::ALL` order
+    pub fn new(sequences: [AnimSequence; AnimState::COUNT]) -> Self {
+        Self {
+            sequences,
+            state: AnimState::Idle,
+            direction: Direction::default(),
+        }
+    }
+
+    /// Get the current state
+    pub fn state(&self) -> AnimState {
+        self.state
+    }
+
+    /// Get the current facing
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Turn to face a direction, keeping the current frame
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    /// Request a change of state
+    /// Returns false if the current state can't be interrupted by it.
+    /// Requesting the current state keeps its sequence running
+    pub fn request(&mut self, state: AnimState) -> bool {
+        if state == self.state {
+            return true;
+        }
+        if !self.state.can_change_to(state) {
+            return false;
+        }
+        self.change(state);
+        true
+    }
+
+    /// Get what to draw for the entity
+    pub fn pose(&self) -> AnimPose {
+        AnimPose {
+            state: self.state,
+            direction: self.direction,
+            frame: self.sequences[self.state as usize].frame().into(),
+        }
+    }
+
+    /// Update the current sequence with a delta time, sending the events of every frame entered
+    /// Returns what to draw for the entity
+    pub fn update<F>(&mut self, delta: f64, emit: F) -> AnimPose
+    where
+        F: FnMut(AnimEvent),
+    {
+        let sequence = &mut self.sequences[self.state as usize];
+        sequence.update(delta, emit);
+        if sequence.is_done() && matches!(self.state, AnimState::Attack | AnimState::Hit) {
+            self.change(AnimState::Idle);
+        }
+        self.pose()
+    }
+
+    fn change(&mut self, state: AnimState) {
+        self.state = state;
+        self.sequences[state as usize].reset();
+    }
+}
+
+/// Placement metadata for the frames of an animated sprite
+/// Keeps every frame lined up on the same anchor point, so characters don't slide or bob between frames
+#[cfg(feature = "render")]
+#[derive(Debug, Clone, Default)]
+pub struct SpriteFrames {
+    pub anchor: Anchor,
+    // Pixel offset of each frame from the anchor, indexed by frame
+    offsets: Vec<Vector2<f32>>,
+}
+
+#[cfg(feature = "render")]
+impl SpriteFrames {
+    /// Create frame metadata where no frame is offset
+    pub fn new(anchor: Anchor) -> Self {
+        Self {
+            anchor,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Create frame metadata with a pixel offset per frame
+    pub fn with_offsets(anchor: Anchor, offsets: Vec<Vector2<f32>>) -> Self {
+        Self { anchor, offsets }
+    }
+
+    /// Get the pixel offset of a frame
+    /// NOTE: Frames without a recorded offset aren't offset at all
+    pub fn offset(&self, frame: Frame) -> Vector2<f32> {
+        self.offsets
+            .get(frame.0)
+            .copied()
+            .unwrap_or_else(Vector2::zero)
+    }
+
+    /// Draw a frame of the animation
+    pub fn draw(
+        &self,
+        batch: &mut Batch,
+        texture: &TextureArray,
+        frame: Frame,
+        xform: Xform2D,
+        color: Vector4<f32>,
+    ) {
+        let offset = self.offset(frame);
+        batch.sprite(texture, frame.0 as u32, self.anchor, offset, xform, color);
+    }
+
+    /// Draw the outline of a frame of the animation
+    /// TODO: Drive this from hover hit-testing once entities exist
+    pub fn draw_outline(
+        &self,
+        batch: &mut Batch,
+        texture: &TextureArray,
+        frame: Frame,
+        xform: Xform2D,
+        color: Vector4<f32>,
+    ) {
+        let offset = self.offset(frame);
+        batch.sprite_outline(texture, frame.0 as u32, self.anchor, offset, xform, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_events() {
+        let mut sequence = AnimSequence::new(10, 0.05, true)
+            .with_event(1, AnimEvent::Sound)
+            .with_event(3, AnimEvent::Footstep)
+            .with_event(7, AnimEvent::Footstep)
+            .with_event(8, AnimEvent::Hit);
+
+        let mut events = Vec::new();
+        // A long update steps through every frame in between
+        let frame: usize = sequence.update(0.41, |event| events.push(event)).into();
+        assert_eq!(8, frame);
+        assert_eq!(
+            vec![
+                AnimEvent::Sound,
+                AnimEvent::Footstep,
+                AnimEvent::Footstep,
+                AnimEvent::Hit
+            ],
+            events
+        );
+
+        // Looping back around sends the early frames' events again
+        events.clear();
+        sequence.update(0.15, |event| events.push(event));
+        assert_eq!(vec![AnimEvent::Sound], events);
+
+        // One-shot sequences hold their last frame without repeating its events
+        let mut sequence = AnimSequence::new(2, 0.05, false).with_event(1, AnimEvent::Hit);
+        let mut hits = 0;
+        sequence.update(1.0, |_| hits += 1);
+        sequence.update(1.0, |_| hits += 1);
+        assert!(sequence.is_done());
+        assert_eq!(1, hits);
+        let frame: usize = sequence.frame().into();
+        assert_eq!(1, frame);
+    }
+
+    #[test]
+    fn test_controller_transitions() {
+        let sequences = AnimState::ALL.map(|state| match state {
+            AnimState::Idle | AnimState::Walk => AnimSequence::new(8, 0.05, true),
+            AnimState::Attack => AnimSequence::new(4, 0.05, false).with_event(2, AnimEvent::Hit),
+            AnimState::Hit | AnimState::Death => AnimSequence::new(4, 0.05, false),
+        });
+        let mut controller = AnimController::new(sequences);
+        controller.set_direction(Direction::West);
+        assert!(controller.request(AnimState::Walk));
+        controller.update(0.12, |_| {});
+        assert_eq!(2, controller.pose().frame);
+
+        // Attacks interrupt walking, start from their first frame and can't be walked out of
+        assert!(controller.request(AnimState::Attack));
+        let pose = controller.pose();
+        assert_eq!(
+            (AnimState::Attack, Direction::West, 0),
+            (pose.state, pose.direction, pose.frame)
+        );
+        assert!(!controller.request(AnimState::Walk));
+
+        // Finished attacks go back to idle
+        let mut hits = 0;
+        controller.update(1.0, |event| {
+            assert_eq!(AnimEvent::Hit, event);
+            hits += 1;
+        });
+        assert_eq!(1, hits);
+        assert_eq!(AnimState::Idle, controller.state());
+
+        // Death is terminal
+        assert!(controller.request(AnimState::Death));
+        controller.update(1.0, |_| {});
+        assert!(!controller.request(AnimState::Idle));
+        assert!(!controller.request(AnimState::Hit));
+        let pose = controller.pose();
+        assert_eq!((AnimState::Death, 3), (pose.state, pose.frame));
+    }
+}
@@ -0,0 +1,247 @@
+use std::mem::size_of;
+use std::sync::Arc;
+
+use mpq::AssetKey;
+
+use super::PcmBuffer;
+
+/// Default memory budget for decoded sound effects, in bytes
+pub const DEFAULT_SOUND_BUDGET: usize = 32 * 1024 * 1024;
+
+/// A cached, decoded sound
+#[derive(Debug)]
+struct CacheEntry {
+    key: AssetKey,
+    sound: Arc<PcmBuffer>,
+    bytes: usize,
+    // Value of the cache's use counter when the sound was last requested
+    last_used: u64,
+}
+
+/// Cache of decoded sound effects, kept under a memory budget
+/// The least recently used sounds are evicted first once the budget is exceeded.
+/// NOTE: Music is too large to decode up front, and should be streamed through a `Resampler` instead
+#[derive(Debug)]
+pub struct SoundCache {
+    // Memory budget and current usage, in bytes
+    budget: usize,
+    used: usize,
+    // Counter used to order the entries by last use
+    uses: u64,
+    entries: Vec<CacheEntry>,
+}
+
+impl SoundCache {
+    /// Create a new, empty cache with a memory budget in bytes
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            uses: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Get the memory budget, in bytes
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Change the memory budget, evicting sounds until the cache fits
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict(0);
+    }
+
+    /// Get the memory used by the cached sounds, in bytes
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Get the number of cached sounds
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Check if a sound is cached, without counting it as used
+    pub fn contains<K: Into<AssetKey>>(&self, key: K) -> bool {
+        let key = key.into();
+        self.entries.iter().any(|entry| entry.key == key)
+    }
+
+    /// Get a cached sound
+    pub fn get<K: Into<AssetKey>>(&mut self, key: K) -> Option<Arc<PcmBuffer>> {
+        let key = key.into();
+        self.uses += 1;
+        let uses = self.uses;
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.key == key)
+            .map(|entry| {
+                entry.last_used = uses;
+                entry.sound.clone()
+            })
+    }
+
+    /// Add a decoded sound to the cache, evicting older sounds to make room
+    /// Sounds larger than the whole budget are returned without being cached.
+    /// NOTE: Evicted sounds stay alive for as long as something (e.g. a playing voice) still holds them
+    pub fn insert<K: Into<AssetKey>>(&mut self, key: K, sound: PcmBuffer) -> Arc<PcmBuffer> {
+        let key = key.into();
+        self.remove(key);
+
+        let bytes = sound.samples.len() * size_of::<f32>();
+        let sound = Arc::new(sound);
+        if bytes > self.budget {
+            return sound;
+        }
+        self.evict(bytes);
+
+        self.uses += 1;
+        self.used += bytes;
+        self.entries.push(CacheEntry {
+            key,
+            sound: sound.clone(),
+            bytes,
+            last_used: self.uses,
+        });
+        sound
+    }
+
+    /// Get a cached sound, decoding and caching it if needed
+    pub fn get_or_load<K, F>(&mut self, key: K, load: F) -> anyhow::Result<Arc<PcmBuffer>>
+    where
+        K: Into<AssetKey>,
+        F: FnOnce() -> anyhow::Result<PcmBuffer>,
+    {
+        let key = key.into();
+        if let Some(sound) = self.get(key) {
+            return Ok(sound);
+        }
+        let sound = load()?;
+        Ok(self.insert(key, sound))
+    }
+
+    /// Decode and cache every sound in a manifest that isn't already cached
+    /// Meant to be called from loading screens, so the first use of a sound doesn't stall a frame
+    pub fn preload<F>(&mut self, manifest: &[&str], mut load: F) -> anyhow::Result<()>
+    where
+        F: FnMut(AssetKey) -> anyhow::Result<PcmBuffer>,
+    {
+        for name in manifest {
+            let key = AssetKey::new(*name);
+            if !self.contains(key) {
+                let sound = load(key)?;
+                self.insert(key, sound);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a sound from the cache
+    pub fn remove<K: Into<AssetKey>>(&mut self, key: K) {
+        let key = key.into();
+        if let Some(index) = self.entries.iter().position(|entry| entry.key == key) {
+            let entry = self.entries.swap_remove(index);
+            self.used -= entry.bytes;
+        }
+    }
+
+    /// Remove every sound from the cache
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    /// Evict the least recently used sounds until there is room for a number of bytes
+    fn evict(&mut self, bytes: usize) {
+        while self.used + bytes > self.budget {
+            let oldest = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(index, _)| index);
+            match oldest {
+                Some(index) => {
+                    let entry = self.entries.swap_remove(index);
+                    self.used -= entry.bytes;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl Default for SoundCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SOUND_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a mono sound taking up a number of bytes
+    fn sound(bytes: usize) -> PcmBuffer {
+        PcmBuffer {
+            sample_rate: 22050,
+            channels: 1,
+            samples: vec![0.0; bytes / size_of::<f32>()],
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = SoundCache::new(300);
+        cache.insert("a", sound(100));
+        cache.insert("b", sound(100));
+        cache.insert("c", sound(100));
+        // Using "a" makes "b" the oldest
+        assert!(cache.get("a").is_some());
+        cache.insert("d", sound(100));
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+        assert!(cache.contains("d"));
+        assert_eq!(300, cache.used());
+
+        // Shrinking the budget evicts in the same order
+        cache.set_budget(200);
+        assert!(!cache.contains("c"));
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn test_oversized_sounds_are_not_cached() {
+        let mut cache = SoundCache::new(100);
+        cache.insert("small", sound(100));
+        let large = cache.insert("large", sound(400));
+        assert_eq!(100, large.samples.len());
+        assert!(!cache.contains("large"));
+        assert!(cache.contains("small"));
+    }
+
+    #[test]
+    fn test_preload() {
+        let mut cache = SoundCache::default();
+        let mut loads = 0;
+        let manifest = ["sfx\\misc\\walk1.wav", "sfx\\misc\\walk2.wav"];
+        for _ in 0..2 {
+            cache
+                .preload(&manifest, |_| {
+                    loads += 1;
+                    Ok(sound(64))
+                })
+                .unwrap();
+        }
+        assert_eq!(2, loads);
+        assert_eq!(2, cache.len());
+    }
+}
@@ -0,0 +1,7 @@
+mod cache;
+mod pcm;
+mod resample;
+
+pub use cache::*;
+pub use pcm::*;
+pub use resample::*;
@@ -0,0 +1,169 @@
+use anyhow::bail;
+
+use super::Resampler;
+
+/// Sample format of raw PCM data
+/// NOTE: These are the formats used by the WAV files in the game archives
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Unsigned 8-bit samples, centered on 128
+    U8,
+    /// Signed, little endian 16-bit samples
+    S16,
+}
+
+impl SampleFormat {
+    /// Get the size of a single sample, in bytes
+    pub fn byte_size(&self) -> usize {
+        match self {
+            SampleFormat::U8 => 1,
+            SampleFormat::S16 => 2,
+        }
+    }
+}
+
+/// Decoded audio, stored as interleaved floating point samples in the range [-1.0, 1.0]
+/// Every decoded asset is converted to this before being mixed
+#[derive(Debug, Clone)]
+pub struct PcmBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+impl PcmBuffer {
+    /// Decode raw, interleaved PCM data
+    pub fn from_bytes(
+        format: SampleFormat,
+        sample_rate: u32,
+        channels: u16,
+        bytes: &[u8],
+    ) -> anyhow::Result<Self> {
+        if sample_rate == 0 || channels == 0 {
+            bail!(
+                "Invalid PCM layout ({} Hz, {} channels)",
+                sample_rate,
+                channels
+            );
+        }
+        let frame_size = format.byte_size() * channels as usize;
+        if !bytes.len().is_multiple_of(frame_size) {
+            bail!("PCM data is not a whole number of frames");
+        }
+        let samples = match format {
+            SampleFormat::U8 => bytes
+                .iter()
+                .map(|sample| (*sample as f32 - 128.0) / 128.0)
+                .collect(),
+            SampleFormat::S16 => bytes
+                .chunks_exact(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0)
+                .collect(),
+        };
+        Ok(Self {
+            sample_rate,
+            channels,
+            samples,
+        })
+    }
+
+    /// Get the number of frames (samples per channel) in the buffer
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Get the length of the buffer, in seconds
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    /// Convert the buffer to a different channel count
+    /// Mono is duplicated into every channel, and down-mixing to mono averages the channels.
+    /// Otherwise, channels are copied across in order and any extra channels are silent
+    pub fn to_channels(&self, channels: u16) -> Self {
+        let (from, to) = (self.channels as usize, channels.max(1) as usize);
+        if from == to {
+            return self.clone();
+        }
+        let mut samples = Vec::with_capacity(self.frames() * to);
+        for frame in self.samples.chunks_exact(from) {
+            if from == 1 {
+                samples.extend(std::iter::repeat_n(frame[0], to));
+            } else if to == 1 {
+                samples.push(frame.iter().sum::<f32>() / from as f32);
+            } else {
+                samples.extend((0..to).map(|channel| frame.get(channel).copied().unwrap_or(0.0)));
+            }
+        }
+        Self {
+            sample_rate: self.sample_rate,
+            channels: to as u16,
+            samples,
+        }
+    }
+
+    /// Resample the buffer to a different sample rate
+    pub fn resample(&self, sample_rate: u32) -> Self {
+        if sample_rate == self.sample_rate {
+            return self.clone();
+        }
+        let mut resampler = Resampler::new(self.sample_rate, sample_rate, self.channels);
+        let mut samples = Vec::with_capacity(resampler.output_len(self.samples.len()));
+        resampler.process(&self.samples, &mut samples);
+        resampler.flush(&mut samples);
+        Self {
+            sample_rate,
+            channels: self.channels,
+            samples,
+        }
+    }
+
+    /// Convert the buffer to the mixer's output layout
+    pub fn convert(&self, sample_rate: u32, channels: u16) -> Self {
+        // Change the channel count last when up-mixing, so fewer samples get resampled
+        if channels > self.channels {
+            self.resample(sample_rate).to_channels(channels)
+        } else {
+            self.to_channels(channels).resample(sample_rate)
+        }
+    }
+
+    /// Get the samples as signed 16-bit integers, clipping anything out of range
+    pub fn to_s16(&self) -> Vec<i16> {
+        self.samples
+            .iter()
+            .map(|sample| (sample.clamp(-1.0, 1.0) * 32767.0).round() as i16)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let pcm = PcmBuffer::from_bytes(SampleFormat::U8, 11025, 1, &[0, 128, 255]).unwrap();
+        assert_eq!(vec![-1.0, 0.0, 127.0 / 128.0], pcm.samples);
+
+        let bytes = [0x00, 0x80, 0x00, 0x00, 0xFF, 0x7F, 0x00, 0x40];
+        let pcm = PcmBuffer::from_bytes(SampleFormat::S16, 22050, 2, &bytes).unwrap();
+        assert_eq!(vec![-1.0, 0.0, 32767.0 / 32768.0, 0.5], pcm.samples);
+        assert_eq!(2, pcm.frames());
+
+        // Half a stereo frame
+        assert!(PcmBuffer::from_bytes(SampleFormat::S16, 22050, 2, &bytes[..6]).is_err());
+    }
+
+    #[test]
+    fn test_to_channels() {
+        let mono = PcmBuffer {
+            sample_rate: 22050,
+            channels: 1,
+            samples: vec![0.5, -0.25],
+        };
+        let stereo = mono.to_channels(2);
+        assert_eq!(vec![0.5, 0.5, -0.25, -0.25], stereo.samples);
+        assert_eq!(mono.samples, stereo.to_channels(1).samples);
+    }
+}
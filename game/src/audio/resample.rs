@@ -0,0 +1,167 @@
+/// Streaming sample rate converter, using linear interpolation
+/// The last input frame is kept between calls, so long streams (music) can be converted in chunks.
+/// NOTE: No low-pass filter is applied, so downsampling can alias. Archive audio is never above the device rate
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    channels: usize,
+    // Sample rates, an output frame advances the input by `from_rate / to_rate` frames
+    from_rate: i64,
+    to_rate: i64,
+    // Position of the next output frame from the start of the next chunk, in 1/to_rate input frames
+    // Kept as an integer so long streams don't drift. Can be as low as -1 frame (-to_rate),
+    // which is the last frame of the previous chunk
+    position: i64,
+    // Last frame of the previous chunk
+    last: Vec<f32>,
+    has_last: bool,
+}
+
+impl Resampler {
+    /// Create a new resampler for interleaved audio with a given number of channels
+    pub fn new(from_rate: u32, to_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            from_rate: from_rate.max(1) as i64,
+            to_rate: to_rate.max(1) as i64,
+            position: 0,
+            last: vec![0.0; channels],
+            has_last: false,
+        }
+    }
+
+    /// Estimate the number of output samples produced by a number of input samples
+    pub fn output_len(&self, input_len: usize) -> usize {
+        let frames = (input_len / self.channels) as u64;
+        let output_frames = (frames * self.to_rate as u64).div_ceil(self.from_rate as u64);
+        output_frames as usize * self.channels
+    }
+
+    /// Resample a chunk of interleaved samples, appending the result to the output
+    /// NOTE: The last output frames of a stream are held back until `flush` is called
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let channels = self.channels;
+        let frames = input.len() / channels;
+        if frames == 0 {
+            return;
+        }
+        loop {
+            let index = self.position.div_euclid(self.to_rate);
+            // Both frames either side of the position are needed
+            if index + 1 >= frames as i64 {
+                break;
+            }
+            let t = self.position.rem_euclid(self.to_rate) as f32 / self.to_rate as f32;
+            for channel in 0..channels {
+                let a = if index < 0 {
+                    self.last[channel]
+                } else {
+                    input[index as usize * channels + channel]
+                };
+                let b = input[(index + 1) as usize * channels + channel];
+                output.push(a + (b - a) * t);
+            }
+            self.position += self.from_rate;
+        }
+        self.position -= frames as i64 * self.to_rate;
+        self.last
+            .copy_from_slice(&input[(frames - 1) * channels..frames * channels]);
+        self.has_last = true;
+    }
+
+    /// Finish the stream, appending any held back frames to the output
+    /// The resampler is reset, and can be reused for a new stream
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        if self.has_last {
+            // There is no next frame to interpolate towards, so hold the last one
+            while self.position < 0 {
+                output.extend_from_slice(&self.last);
+                self.position += self.from_rate;
+            }
+        }
+        self.reset();
+    }
+
+    /// Reset the resampler for a new stream, dropping any held back frames
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.last.iter_mut().for_each(|sample| *sample = 0.0);
+        self.has_last = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::PI;
+
+    /// Generate one second of a mono sine tone
+    fn tone(frequency: f32, sample_rate: u32) -> Vec<f32> {
+        (0..sample_rate)
+            .map(|i| (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// Count the rising zero crossings of a mono signal
+    fn rising_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    fn resample(input: &[f32], from_rate: u32, to_rate: u32, channels: u16) -> Vec<f32> {
+        let mut resampler = Resampler::new(from_rate, to_rate, channels);
+        let mut output = Vec::new();
+        resampler.process(input, &mut output);
+        resampler.flush(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_resample_pitch() {
+        for (from_rate, to_rate) in [(22050, 44100), (11025, 48000), (44100, 22050)] {
+            let output = resample(&tone(440.0, from_rate), from_rate, to_rate, 1);
+            // One second of input is still one second of output
+            assert_eq!(to_rate as usize, output.len());
+            // ...and still a 440 Hz tone
+            let crossings = rising_crossings(&output) as i32;
+            assert!(
+                (crossings - 440).abs() <= 1,
+                "{} -> {} Hz: {} crossings",
+                from_rate,
+                to_rate,
+                crossings
+            );
+        }
+    }
+
+    #[test]
+    fn test_resample_stereo() {
+        // Left and right channels must not bleed into each other
+        let input: Vec<f32> = (0..100).flat_map(|_| [1.0, -1.0]).collect();
+        let output = resample(&input, 22050, 44100, 2);
+        assert_eq!(400, output.len());
+        for frame in output.chunks_exact(2) {
+            assert_eq!([1.0, -1.0], frame);
+        }
+    }
+
+    #[test]
+    fn test_resample_chunked() {
+        let input = tone(440.0, 22050);
+        let expected = resample(&input, 22050, 48000, 1);
+
+        let mut resampler = Resampler::new(22050, 48000, 1);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1000) {
+            resampler.process(chunk, &mut output);
+        }
+        resampler.flush(&mut output);
+        assert_eq!(expected.len(), output.len());
+        for (a, b) in expected.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
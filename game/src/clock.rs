@@ -0,0 +1,243 @@
+/// Slowest allowed simulation speed, as a multiple of real time
+pub const MIN_TIME_SCALE: f64 = 0.25;
+/// Fastest allowed simulation speed, as a multiple of real time
+pub const MAX_TIME_SCALE: f64 = 4.0;
+
+/// Longest frame time handed to the simulation, in seconds
+/// Longer frames (e.g. after a debugger pause) are clamped, so the simulation doesn't fall
+/// further behind trying to catch up
+pub const MAX_FRAME_DELTA: f64 = 0.25;
+/// Frame times this close to a whole number of ticks are rounded to it, in seconds
+/// Keeps v-synced frames from drifting in and out of phase with the simulation
+pub const VSYNC_SNAP_TOLERANCE: f64 = 0.0002;
+
+/// Source of the current time for a game clock
+pub trait TimeSource {
+    /// Get the current time, in seconds from an arbitrary starting point
+    fn now(&self) -> f64;
+}
+
+#[cfg(feature = "render")]
+impl TimeSource for glfw::Glfw {
+    fn now(&self) -> f64 {
+        self.get_time()
+    }
+}
+
+/// Manually advanced time source, for tests and tools
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManualTime {
+    time: f64,
+}
+
+impl ManualTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the time forward
+    pub fn advance(&mut self, delta: f64) {
+        self.time += delta;
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> f64 {
+        self.time
+    }
+}
+
+/// Fixed-step game clock
+/// Accumulates real frame time and hands it out to the simulation in fixed-size ticks.
+/// Also supports pausing, single-stepping and scaling time for debugging.
+#[derive(Debug)]
+pub struct GameClock {
+    // Length of a single simulation tick, in seconds
+    step: f64,
+    // Scaled time that has not yet been consumed by a tick
+    accumulator: f64,
+    // Multiplier applied to the real time passed to `advance`
+    time_scale: f64,
+    // Paused clocks only tick when a step is requested
+    paused: bool,
+    // Number of single ticks requested while paused
+    pending_steps: usize,
+    // Time of the last update, from the time source
+    last_time: Option<f64>,
+    // Number of ticks since the clock was created
+    ticks: u64,
+}
+
+impl GameClock {
+    /// Create a new clock that ticks every `step` seconds
+    pub fn new(step: f64) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            last_time: None,
+            ticks: 0,
+        }
+    }
+
+    /// Get the length of a single tick, in seconds
+    /// NOTE: This is always the unscaled step, so the simulation stays deterministic
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Get the number of ticks since the clock was created
+    /// Deterministic systems should count time in ticks rather than seconds
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Read the time source, and add the time elapsed since the last update to the clock
+    /// The first update only records the time. Returns the elapsed real time
+    pub fn update<T: TimeSource>(&mut self, source: &T) -> f64 {
+        let now = source.now();
+        let delta = self.last_time.map_or(0.0, |last_time| now - last_time);
+        self.last_time = Some(now);
+        self.advance(delta);
+        delta
+    }
+
+    /// Add real elapsed time to the clock
+    pub fn advance(&mut self, delta: f64) {
+        // Time spent paused is dropped, otherwise un-pausing would replay it all at once
+        if !self.paused {
+            let delta = self.snap(delta.clamp(0.0, MAX_FRAME_DELTA));
+            self.accumulator += delta * self.time_scale;
+        }
+    }
+
+    /// Round frame times close to a whole number of ticks to it
+    fn snap(&self, delta: f64) -> f64 {
+        let ticks = (delta / self.step).round();
+        if ticks >= 1.0 && (delta - ticks * self.step).abs() < VSYNC_SNAP_TOLERANCE {
+            ticks * self.step
+        } else {
+            delta
+        }
+    }
+
+    /// Consume a single tick from the clock
+    /// Returns true if the simulation should be updated by one step
+    pub fn tick(&mut self) -> bool {
+        if self.paused {
+            if self.pending_steps > 0 {
+                self.pending_steps -= 1;
+                self.ticks += 1;
+                return true;
+            }
+            return false;
+        }
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            self.ticks += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check if the clock is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pause or resume the clock
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+        self.accumulator = 0.0;
+    }
+
+    /// Toggle between paused and running
+    pub fn toggle_paused(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    /// Request a single tick of the simulation
+    /// NOTE: Only has an effect while paused
+    pub fn step_once(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Get the current time scale
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Set the time scale, clamped to [MIN_TIME_SCALE, MAX_TIME_SCALE]
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    /// Double the speed of the simulation
+    pub fn speed_up(&mut self) {
+        self.set_time_scale(self.time_scale * 2.0);
+    }
+
+    /// Halve the speed of the simulation
+    pub fn slow_down(&mut self) {
+        self.set_time_scale(self.time_scale * 0.5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f64 = 1.0 / 60.0;
+
+    /// Count the ticks available on the clock
+    fn run(clock: &mut GameClock) -> usize {
+        let mut ticks = 0;
+        while clock.tick() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn test_vsync_frames_dont_drift() {
+        let mut time = ManualTime::new();
+        let mut clock = GameClock::new(STEP);
+        clock.update(&time);
+        // A 59.94 Hz display would drop a tick every ~17 seconds without snapping
+        for _ in 0..6000 {
+            time.advance(1.0 / 59.94);
+            clock.update(&time);
+            assert_eq!(1, run(&mut clock));
+        }
+        assert_eq!(6000, clock.ticks());
+    }
+
+    #[test]
+    fn test_long_frames_are_clamped() {
+        let mut time = ManualTime::new();
+        let mut clock = GameClock::new(STEP);
+        clock.update(&time);
+        // Sitting on a breakpoint for 30 seconds
+        time.advance(30.0);
+        assert_eq!(30.0, clock.update(&time));
+        assert_eq!((MAX_FRAME_DELTA / STEP) as usize, run(&mut clock));
+    }
+
+    #[test]
+    fn test_paused_steps_are_counted() {
+        let mut clock = GameClock::new(STEP);
+        clock.set_paused(true);
+        clock.advance(1.0);
+        assert_eq!(0, run(&mut clock));
+        clock.step_once();
+        clock.step_once();
+        assert_eq!(2, run(&mut clock));
+        assert_eq!(2, clock.ticks());
+    }
+}
@@ -0,0 +1,244 @@
+use cgmath::{vec2, Vector2};
+
+use crate::direction::Direction;
+use crate::RenderSize;
+
+/// Size of a sub-tile diamond on the screen, in pixels
+/// Sub-tiles are the walkable positions of the map; the tiles of the level files are 2x2 sub-tiles
+pub const SUBTILE_WIDTH: i32 = 64;
+pub const SUBTILE_HEIGHT: i32 = 32;
+/// Number of sub-tiles along each side of a tile
+pub const SUBTILES_PER_TILE: i32 = 2;
+
+// Coordinate spaces, from the map to the screen:
+// - Tiles: the grid of the level files, each tile made of 2x2 sub-tiles
+// - Sub-tiles: walkable positions. Fractional sub-tile positions place entities between them
+// - World pixels: the isometric projection of sub-tile positions. Sub-tile (0, 0) is centered on
+//   the origin, the x axis points down-right and the y axis down-left on the screen
+// - Screen pixels: world pixels relative to a camera, in the render area with y down
+
+/// Position of a tile on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TilePos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl TilePos {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Get the top sub-tile of the tile, which has the lowest coordinates
+    pub fn subtile(&self) -> SubtilePos {
+        SubtilePos::new(self.x * SUBTILES_PER_TILE, self.y * SUBTILES_PER_TILE)
+    }
+
+    /// Get the 4 sub-tiles of the tile, in row order
+    pub fn subtiles(&self) -> [SubtilePos; 4] {
+        let top = self.subtile();
+        [
+            top,
+            SubtilePos::new(top.x + 1, top.y),
+            SubtilePos::new(top.x, top.y + 1),
+            SubtilePos::new(top.x + 1, top.y + 1),
+        ]
+    }
+}
+
+/// Walkable position on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SubtilePos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl SubtilePos {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Get the tile the sub-tile is part of
+    pub fn tile(&self) -> TilePos {
+        TilePos::new(
+            self.x.div_euclid(SUBTILES_PER_TILE),
+            self.y.div_euclid(SUBTILES_PER_TILE),
+        )
+    }
+
+    /// Get the neighbouring sub-tile in a direction
+    pub fn step(&self, direction: Direction) -> Self {
+        let (dx, dy) = direction.tile_offset();
+        Self::new(self.x + dx, self.y + dy)
+    }
+
+    /// Get the sub-tile position as a vector, for fractional positions
+    pub fn to_vec(&self) -> Vector2<f32> {
+        vec2(self.x as f32, self.y as f32)
+    }
+
+    /// Get the world position of the center of the sub-tile
+    pub fn world(&self) -> Vector2<f32> {
+        subtile_to_world(self.to_vec())
+    }
+
+    /// Get the sub-tile whose diamond contains a world position
+    pub fn from_world(world: Vector2<f32>) -> Self {
+        let pos = world_to_subtile(world);
+        // Sub-tile centers are on whole coordinates, so the diamond edges are halfway between them
+        Self::new((pos.x + 0.5).floor() as i32, (pos.y + 0.5).floor() as i32)
+    }
+}
+
+/// Convert a (fractional) sub-tile position to world pixels
+pub fn subtile_to_world(pos: Vector2<f32>) -> Vector2<f32> {
+    vec2(
+        (pos.x - pos.y) * (SUBTILE_WIDTH / 2) as f32,
+        (pos.x + pos.y) * (SUBTILE_HEIGHT / 2) as f32,
+    )
+}
+
+/// Convert world pixels to a fractional sub-tile position
+pub fn world_to_subtile(world: Vector2<f32>) -> Vector2<f32> {
+    let u = world.x / SUBTILE_WIDTH as f32;
+    let v = world.y / SUBTILE_HEIGHT as f32;
+    vec2(v + u, v - u)
+}
+
+/// View of the world, centered on a world position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// World position shown in the middle of the render area
+    pub center: Vector2<f32>,
+    pub size: RenderSize,
+}
+
+impl Camera {
+    pub fn new(center: Vector2<f32>, size: RenderSize) -> Self {
+        Self { center, size }
+    }
+
+    /// Create a camera centered on a sub-tile
+    pub fn looking_at(pos: SubtilePos, size: RenderSize) -> Self {
+        Self::new(pos.world(), size)
+    }
+
+    fn half_size(&self) -> Vector2<f32> {
+        vec2(self.size.width as f32 * 0.5, self.size.height as f32 * 0.5)
+    }
+
+    /// Convert world pixels to screen pixels
+    pub fn world_to_screen(&self, world: Vector2<f32>) -> Vector2<f32> {
+        world - self.center + self.half_size()
+    }
+
+    /// Convert screen pixels to world pixels
+    pub fn screen_to_world(&self, screen: Vector2<f32>) -> Vector2<f32> {
+        screen - self.half_size() + self.center
+    }
+
+    /// Get the screen position of the center of a sub-tile
+    pub fn subtile_to_screen(&self, pos: SubtilePos) -> Vector2<f32> {
+        self.world_to_screen(pos.world())
+    }
+
+    /// Get the sub-tile under a screen position, like the mouse cursor
+    /// NOTE: This picks the floor, ignoring anything drawn standing up from it
+    pub fn pick(&self, screen: Vector2<f32>) -> SubtilePos {
+        SubtilePos::from_world(self.screen_to_world(screen))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(expected: Vector2<f32>, actual: Vector2<f32>) {
+        assert!(
+            (expected.x - actual.x).abs() < 1e-4 && (expected.y - actual.y).abs() < 1e-4,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_tiles() {
+        let tile = TilePos::new(3, -2);
+        assert_eq!(SubtilePos::new(6, -4), tile.subtile());
+        for subtile in tile.subtiles() {
+            assert_eq!(tile, subtile.tile());
+        }
+        assert_eq!(TilePos::new(-1, -1), SubtilePos::new(-1, -2).tile());
+        assert_eq!(TilePos::new(0, 0), SubtilePos::new(1, 1).tile());
+    }
+
+    #[test]
+    fn test_world() {
+        assert_near(vec2(0.0, 0.0), SubtilePos::new(0, 0).world());
+        // One step along each axis moves half a diamond across and down
+        assert_near(vec2(32.0, 16.0), SubtilePos::new(1, 0).world());
+        assert_near(vec2(-32.0, 16.0), SubtilePos::new(0, 1).world());
+        assert_near(
+            vec2(0.0, 32.0),
+            SubtilePos::new(0, 0).step(Direction::South).world(),
+        );
+        assert_near(
+            vec2(-64.0, 0.0),
+            SubtilePos::new(0, 0).step(Direction::West).world(),
+        );
+        // Halfway through a step
+        assert_near(vec2(16.0, 8.0), subtile_to_world(vec2(0.5, 0.0)));
+
+        for y in -3..=3 {
+            for x in -3..=3 {
+                let pos = SubtilePos::new(x, y);
+                let world = pos.world();
+                assert_near(pos.to_vec(), world_to_subtile(world));
+                assert_eq!(pos, SubtilePos::from_world(world));
+                // Points just inside each corner of the diamond belong to it
+                for corner in [
+                    vec2(31.0, 0.0),
+                    vec2(-31.0, 0.0),
+                    vec2(0.0, 15.0),
+                    vec2(0.0, -15.0),
+                ] {
+                    assert_eq!(pos, SubtilePos::from_world(world + corner));
+                }
+            }
+        }
+        // Just past the lower-right edge is the next sub-tile along x
+        assert_eq!(
+            SubtilePos::new(1, 0),
+            SubtilePos::from_world(vec2(17.0, 9.0))
+        );
+    }
+
+    #[test]
+    fn test_camera() {
+        let camera = Camera::looking_at(SubtilePos::new(10, 4), RenderSize::STANDARD);
+        assert_near(
+            vec2(320.0, 240.0),
+            camera.subtile_to_screen(SubtilePos::new(10, 4)),
+        );
+        assert_near(
+            vec2(352.0, 256.0),
+            camera.subtile_to_screen(SubtilePos::new(11, 4)),
+        );
+        assert_eq!(SubtilePos::new(10, 4), camera.pick(vec2(320.0, 240.0)));
+        assert_eq!(SubtilePos::new(10, 5), camera.pick(vec2(288.0, 256.0)));
+        let screen = vec2(12.5, 470.0);
+        assert_near(
+            screen,
+            camera.world_to_screen(camera.screen_to_world(screen)),
+        );
+
+        // Widescreen shows more of the world on the sides, around the same center
+        let wide = Camera::new(camera.center, RenderSize::WIDESCREEN);
+        assert_near(
+            vec2(480.0, 240.0),
+            wide.subtile_to_screen(SubtilePos::new(10, 4)),
+        );
+        assert_eq!(SubtilePos::new(10, 4), wide.pick(vec2(480.0, 240.0)));
+    }
+}
@@ -0,0 +1,222 @@
+use std::f32::consts::TAU;
+
+use cgmath::{vec2, Vector2};
+
+/// Facing of an entity on the isometric map
+/// Ordered like the groups of the game's CL2 sprites, starting facing the screen and going clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Direction {
+    #[default]
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+}
+
+impl Direction {
+    pub const COUNT: usize = 8;
+    /// Every direction, in sprite group order
+    pub const ALL: [Direction; Direction::COUNT] = [
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+    ];
+
+    /// Get the sprite group this direction is drawn with
+    pub fn group(&self) -> usize {
+        *self as usize
+    }
+
+    /// Get the facing of a movement in screen space (y down), or None if there's no movement
+    pub fn from_screen_vector(v: Vector2<f32>) -> Option<Self> {
+        sector(unskew(v), Self::COUNT).map(|sector| Self::ALL[sector])
+    }
+
+    /// Get the facing from one screen position to another, like the player to the mouse cursor
+    pub fn toward(from: Vector2<f32>, to: Vector2<f32>) -> Option<Self> {
+        Self::from_screen_vector(to - from)
+    }
+
+    /// Get the facing of a movement across tiles, or None if there's no movement
+    /// Deltas that aren't to a neighbouring tile get the closest facing
+    pub fn from_tile_delta(dx: i32, dy: i32) -> Option<Self> {
+        sector(tile_to_unskewed(dx, dy), Self::COUNT).map(|sector| Self::ALL[sector])
+    }
+
+    /// Get the offset to the neighbouring tile in this direction
+    pub fn tile_offset(&self) -> (i32, i32) {
+        match self {
+            Direction::South => (1, 1),
+            Direction::SouthWest => (0, 1),
+            Direction::West => (-1, 1),
+            Direction::NorthWest => (-1, 0),
+            Direction::North => (-1, -1),
+            Direction::NorthEast => (0, -1),
+            Direction::East => (1, -1),
+            Direction::SouthEast => (1, 0),
+        }
+    }
+
+    /// Get the direction turned a number of eighth turns clockwise (negative is counter-clockwise)
+    pub fn rotate(&self, steps: i32) -> Self {
+        let index = (*self as i32 + steps).rem_euclid(Self::COUNT as i32);
+        Self::ALL[index as usize]
+    }
+
+    /// Get the direction facing the other way
+    pub fn opposite(&self) -> Self {
+        self.rotate(4)
+    }
+}
+
+/// Facing of a missile, in sixteenth turns
+/// Ordered like the groups of 16-direction missile sprites, starting facing the screen and going clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Direction16(u8);
+
+impl Direction16 {
+    pub const COUNT: usize = 16;
+
+    /// Get the sprite group this direction is drawn with
+    pub fn group(&self) -> usize {
+        self.0 as usize
+    }
+
+    /// Get the facing of a movement in screen space (y down), or None if there's no movement
+    pub fn from_screen_vector(v: Vector2<f32>) -> Option<Self> {
+        sector(unskew(v), Self::COUNT).map(|sector| Self(sector as u8))
+    }
+
+    /// Get the facing from one screen position to another, like a caster to the mouse cursor
+    pub fn toward(from: Vector2<f32>, to: Vector2<f32>) -> Option<Self> {
+        Self::from_screen_vector(to - from)
+    }
+
+    /// Get the closest of the 8 directions
+    /// NOTE: Halfway facings round clockwise
+    pub fn to_direction(&self) -> Direction {
+        Direction::ALL[(self.0 as usize).div_ceil(2) % Direction::COUNT]
+    }
+}
+
+impl From<Direction> for Direction16 {
+    fn from(direction: Direction) -> Self {
+        Self(direction as u8 * 2)
+    }
+}
+
+/// Undo the isometric skew of a screen space vector
+/// Tiles are drawn twice as wide as they are tall, so doubling the height puts the tile edges and
+/// corners at even eighth turns
+fn unskew(v: Vector2<f32>) -> Vector2<f32> {
+    vec2(v.x, v.y * 2.0)
+}
+
+/// Get a tile delta as an unskewed screen space vector
+/// The tile x axis points down-right on the screen, and the y axis down-left
+fn tile_to_unskewed(dx: i32, dy: i32) -> Vector2<f32> {
+    vec2((dx - dy) as f32, (dx + dy) as f32)
+}
+
+/// Get the sector an unskewed vector points into, out of `count` even sectors
+/// Sectors are centered on their direction, starting at straight down and going clockwise
+fn sector(v: Vector2<f32>, count: usize) -> Option<usize> {
+    if v.x == 0.0 && v.y == 0.0 {
+        return None;
+    }
+    // Clockwise angle from straight down, with y down
+    let angle = f32::atan2(-v.x, v.y).rem_euclid(TAU);
+    let sector = (angle / (TAU / count as f32)).round() as usize;
+    Some(sector % count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_directions() {
+        for direction in Direction::ALL {
+            let (dx, dy) = direction.tile_offset();
+            assert_eq!(Some(direction), Direction::from_tile_delta(dx, dy));
+            // Longer moves along the same line keep the facing
+            assert_eq!(Some(direction), Direction::from_tile_delta(dx * 5, dy * 5));
+            // A neighbouring tile is drawn along the same screen direction
+            let screen = vec2((dx - dy) as f32 * 32.0, (dx + dy) as f32 * 16.0);
+            assert_eq!(Some(direction), Direction::from_screen_vector(screen));
+            assert_eq!(direction, direction.opposite().opposite());
+            let (ox, oy) = direction.opposite().tile_offset();
+            assert_eq!((-dx, -dy), (ox, oy));
+            assert_eq!(direction.group() * 2, Direction16::from(direction).group());
+            assert_eq!(direction, Direction16::from(direction).to_direction());
+        }
+        assert_eq!(None, Direction::from_tile_delta(0, 0));
+        assert_eq!(Some(Direction::SouthEast), Direction::from_tile_delta(3, 1));
+        assert_eq!(Direction::SouthWest, Direction::South.rotate(1));
+        assert_eq!(Direction::SouthEast, Direction::South.rotate(-1));
+        assert_eq!(Direction::South, Direction::South.rotate(8));
+    }
+
+    #[test]
+    fn test_screen_directions() {
+        let zero = vec2(0.0, 0.0);
+        assert_eq!(None, Direction::from_screen_vector(zero));
+        assert_eq!(None, Direction16::from_screen_vector(zero));
+        assert_eq!(
+            Some(Direction::South),
+            Direction::toward(vec2(320.0, 240.0), vec2(320.0, 300.0))
+        );
+        assert_eq!(
+            Some(Direction::West),
+            Direction::from_screen_vector(vec2(-1.0, 0.0))
+        );
+        assert_eq!(
+            Some(Direction::North),
+            Direction::from_screen_vector(vec2(0.0, -1.0))
+        );
+        assert_eq!(
+            Some(Direction::East),
+            Direction::from_screen_vector(vec2(1.0, 0.0))
+        );
+        // Along a tile edge, which is only half as steep as a 45 degree line on the screen
+        assert_eq!(
+            Some(Direction::SouthEast),
+            Direction::from_screen_vector(vec2(2.0, 1.0))
+        );
+        assert_eq!(
+            Some(Direction::NorthWest),
+            Direction::from_screen_vector(vec2(-2.0, -1.0))
+        );
+        // The SE sector ends a bit past a 45 degree line on the screen, halfway to straight down
+        assert_eq!(
+            Some(Direction::South),
+            Direction::from_screen_vector(vec2(1.0, 1.5))
+        );
+        assert_eq!(
+            Some(Direction::SouthEast),
+            Direction::from_screen_vector(vec2(1.0, 1.0))
+        );
+
+        // Every sixteenth turn, counted clockwise from straight down in unskewed space
+        for group in 0..Direction16::COUNT {
+            let angle = group as f32 * TAU / Direction16::COUNT as f32;
+            let screen = vec2(-angle.sin(), angle.cos() * 0.5);
+            let direction = Direction16::from_screen_vector(screen).unwrap();
+            assert_eq!(group, direction.group());
+            // Slightly off the exact facing still picks it
+            let nudged = vec2(-(angle + 0.1).sin(), (angle + 0.1).cos() * 0.5);
+            assert_eq!(Some(direction), Direction16::from_screen_vector(nudged));
+        }
+        assert_eq!(Direction::SouthWest, Direction16(1).to_direction());
+        assert_eq!(Direction::South, Direction16(15).to_direction());
+    }
+}
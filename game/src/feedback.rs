@@ -0,0 +1,200 @@
+#[cfg(feature = "render")]
+use cgmath::Vector4;
+use cgmath::{vec2, Vector2};
+
+#[cfg(feature = "render")]
+use gfx::{Batch, RenderPass};
+
+use crate::coords::Camera;
+#[cfg(feature = "render")]
+use crate::{RenderSize, RENDER_HEIGHT, RENDER_WIDTH};
+
+/// Largest camera offset of a full strength shake, in pixels
+const MAX_SHAKE_OFFSET: f32 = 8.0;
+/// Shake strength lost per second
+const SHAKE_DECAY: f32 = 1.5;
+/// Shake frequencies along each axis, in radians per second
+/// Different frequencies keep the camera from shaking along a straight line
+const SHAKE_FREQUENCY: (f64, f64) = (47.0, 61.0);
+
+/// Largest opacity of the damage flash
+const MAX_FLASH_ALPHA: f32 = 0.35;
+/// Flash strength lost per second
+const FLASH_DECAY: f32 = 4.0;
+
+/// Camera shake from boss hits and explosions
+/// Impulses add up to a strength of 1.0, which falls off over time. The offset grows with the
+/// square of the strength, so small impulses barely move the camera.
+#[derive(Debug, Clone)]
+pub struct CameraShake {
+    enabled: bool,
+    strength: f32,
+    // Time the shake has been running, to step the oscillation
+    time: f64,
+}
+
+impl CameraShake {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            strength: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Enable or disable shaking, stopping any current shake
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.strength = 0.0;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Add an impulse to the shake, from 0.0 (none) to 1.0 (full strength)
+    pub fn impulse(&mut self, strength: f32) {
+        if self.enabled {
+            self.strength = (self.strength + strength.max(0.0)).min(1.0);
+        }
+    }
+
+    /// Get the current strength, from 0.0 to 1.0
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    pub fn update(&mut self, delta: f64) {
+        self.strength = (self.strength - SHAKE_DECAY * delta as f32).max(0.0);
+        self.time = if self.strength > 0.0 {
+            self.time + delta
+        } else {
+            0.0
+        };
+    }
+
+    /// Get the current camera offset, in world pixels
+    pub fn offset(&self) -> Vector2<f32> {
+        let amplitude = MAX_SHAKE_OFFSET * self.strength * self.strength;
+        vec2(
+            (self.time * SHAKE_FREQUENCY.0).sin() as f32,
+            (self.time * SHAKE_FREQUENCY.1).cos() as f32,
+        ) * amplitude
+    }
+
+    /// Get a camera moved by the current shake
+    pub fn apply(&self, camera: Camera) -> Camera {
+        Camera::new(camera.center + self.offset(), camera.size)
+    }
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+/// Red flash over the world when the player takes damage
+#[derive(Debug, Clone)]
+pub struct DamageFlash {
+    enabled: bool,
+    strength: f32,
+}
+
+impl DamageFlash {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            strength: 0.0,
+        }
+    }
+
+    /// Enable or disable flashing, stopping any current flash
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.strength = 0.0;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start a flash, from 0.0 (none) to 1.0 (full strength)
+    /// A stronger flash replaces a weaker one, but flashes don't add up
+    pub fn trigger(&mut self, strength: f32) {
+        if self.enabled {
+            self.strength = self.strength.max(strength.clamp(0.0, 1.0));
+        }
+    }
+
+    pub fn update(&mut self, delta: f64) {
+        self.strength = (self.strength - FLASH_DECAY * delta as f32).max(0.0);
+    }
+
+    /// Get the current opacity of the flash
+    pub fn alpha(&self) -> f32 {
+        MAX_FLASH_ALPHA * self.strength
+    }
+
+    /// Draw the flash over the whole render area, on top of the world and under the UI
+    #[cfg(feature = "render")]
+    pub fn render(&self, batch: &mut Batch, render_size: RenderSize) {
+        if self.strength <= 0.0 {
+            return;
+        }
+        let (pass, uniform_set) = (batch.pass(), batch.uniform_set());
+        // The overlay pass is alpha blended, so the flash tints the world instead of brightening it
+        batch.set_pass(RenderPass::Overlay);
+        // The UI area is centered in the render area, so its center is the render area's
+        batch.aabb(
+            vec2(RENDER_WIDTH as f32 * 0.5, RENDER_HEIGHT as f32 * 0.5),
+            vec2(render_size.width as f32, render_size.height as f32),
+            Vector4::new(1.0, 0.0, 0.0, self.alpha()),
+        );
+        // Setting the pass resets the uniform set, so restore any override the caller made
+        batch.set_pass(pass);
+        batch.set_uniform_set(uniform_set);
+    }
+}
+
+impl Default for DamageFlash {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_falloff() {
+        let mut shake = CameraShake::default();
+        shake.impulse(0.75);
+        shake.impulse(0.75);
+        assert_eq!(1.0, shake.strength());
+        shake.update(0.1);
+        let offset = shake.offset();
+        assert!(offset.x.abs() <= MAX_SHAKE_OFFSET && offset.y.abs() <= MAX_SHAKE_OFFSET);
+        assert!(offset.x != 0.0 || offset.y != 0.0);
+        // Shakes die down and leave the camera where it was
+        shake.update(1.0);
+        assert_eq!(0.0, shake.strength());
+        assert_eq!(vec2(0.0, 0.0), shake.offset());
+
+        let mut flash = DamageFlash::default();
+        flash.trigger(1.0);
+        flash.trigger(0.5);
+        assert_eq!(MAX_FLASH_ALPHA, flash.alpha());
+        flash.update(0.1);
+        assert!(flash.alpha() < MAX_FLASH_ALPHA && flash.alpha() > 0.0);
+
+        // Disabled effects ignore hits
+        shake.set_enabled(false);
+        shake.impulse(1.0);
+        assert_eq!(0.0, shake.strength());
+        flash.set_enabled(false);
+        flash.trigger(1.0);
+        assert_eq!(0.0, flash.alpha());
+    }
+}
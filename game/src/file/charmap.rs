@@ -0,0 +1,80 @@
+/*
+NOTES:
+The bitmap fonts are laid out in the Windows-1252 code page, which matches Latin-1 (and therefore the
+first 256 unicode code points) everywhere except 0x80..0x9F.
+*/
+
+/// Unicode characters stored in the 0x80..0x9F range of Windows-1252
+/// NOTE: Unused slots in the code page are stored as '\0'
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\0', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\0', '\u{017D}', '\0', '\0',
+    '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}', '\u{02DC}',
+    '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\0', '\u{017E}', '\u{0178}',
+];
+
+/// Replacements for common characters that don't exist in Windows-1252
+/// Accented letters map to their base letter, punctuation to its closest ASCII equivalent
+#[rustfmt::skip]
+const TRANSLITERATIONS: &[(char, char)] = &[
+    ('Ā', 'A'), ('ā', 'a'), ('Ă', 'A'), ('ă', 'a'), ('Ą', 'A'), ('ą', 'a'),
+    ('Ć', 'C'), ('ć', 'c'), ('Č', 'C'), ('č', 'c'), ('Ď', 'D'), ('ď', 'd'),
+    ('Đ', 'D'), ('đ', 'd'), ('Ē', 'E'), ('ē', 'e'), ('Ė', 'E'), ('ė', 'e'),
+    ('Ę', 'E'), ('ę', 'e'), ('Ě', 'E'), ('ě', 'e'), ('Ğ', 'G'), ('ğ', 'g'),
+    ('Ģ', 'G'), ('ģ', 'g'), ('Ī', 'I'), ('ī', 'i'), ('Į', 'I'), ('į', 'i'),
+    ('İ', 'I'), ('ı', 'i'), ('Ķ', 'K'), ('ķ', 'k'), ('Ĺ', 'L'), ('ĺ', 'l'),
+    ('Ļ', 'L'), ('ļ', 'l'), ('Ľ', 'L'), ('ľ', 'l'), ('Ł', 'L'), ('ł', 'l'),
+    ('Ń', 'N'), ('ń', 'n'), ('Ņ', 'N'), ('ņ', 'n'), ('Ň', 'N'), ('ň', 'n'),
+    ('Ō', 'O'), ('ō', 'o'), ('Ő', 'O'), ('ő', 'o'), ('Ŕ', 'R'), ('ŕ', 'r'),
+    ('Ř', 'R'), ('ř', 'r'), ('Ś', 'S'), ('ś', 's'), ('Ş', 'S'), ('ş', 's'),
+    ('Ș', 'S'), ('ș', 's'), ('Ţ', 'T'), ('ţ', 't'), ('Ț', 'T'), ('ț', 't'),
+    ('Ť', 'T'), ('ť', 't'), ('Ū', 'U'), ('ū', 'u'), ('Ů', 'U'), ('ů', 'u'),
+    ('Ű', 'U'), ('ű', 'u'), ('Ų', 'U'), ('ų', 'u'), ('Ź', 'Z'), ('ź', 'z'),
+    ('Ż', 'Z'), ('ż', 'z'),
+    ('\u{2010}', '-'), ('\u{2011}', '-'), ('\u{2012}', '-'), ('\u{2015}', '-'),
+    ('\u{2032}', '\''), ('\u{2033}', '"'), ('\u{2212}', '-'),
+];
+
+/// Encode a character as its Windows-1252 byte, if it has one
+pub(crate) fn encode_cp1252(c: char) -> Option<u8> {
+    match c as u32 {
+        // C1 control codes never have a glyph
+        0x80..=0x9F => None,
+        code @ 0x00..=0xFF => Some(code as u8),
+        _ => CP1252_HIGH
+            .iter()
+            .position(|high| *high == c)
+            .map(|index| 0x80 + index as u8),
+    }
+}
+
+/// Get the closest Windows-1252 representable replacement for a character, if one is known
+pub(crate) fn transliterate(c: char) -> Option<char> {
+    TRANSLITERATIONS
+        .iter()
+        .find(|(from, _)| *from == c)
+        .map(|(_, to)| *to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_cp1252() {
+        assert_eq!(Some(b'A'), encode_cp1252('A'));
+        assert_eq!(Some(0xE9), encode_cp1252('é'));
+        assert_eq!(Some(0x80), encode_cp1252('€'));
+        assert_eq!(Some(0x92), encode_cp1252('’'));
+        assert_eq!(Some(0x9F), encode_cp1252('Ÿ'));
+        assert_eq!(None, encode_cp1252('\u{0085}'));
+        assert_eq!(None, encode_cp1252('ł'));
+    }
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(Some('l'), transliterate('ł'));
+        assert_eq!(Some('O'), transliterate('Ő'));
+        assert_eq!(None, transliterate('é'));
+    }
+}
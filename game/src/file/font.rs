@@ -1,14 +1,18 @@
-use std::str::Chars;
+use std::ops::Deref;
+use std::sync::Mutex;
 
 use anyhow::Context;
 
-use cgmath::Vector2;
+use cgmath::{Vector2, Vector4};
 
-use mpq::Archive;
+use mpq::{Archive, AssetKey};
 
 use gfx::*;
 
 use crate::file::Image;
+use crate::profile;
+
+use super::charmap;
 
 /*
 NOTES:
@@ -35,7 +39,7 @@ Font Filenames in DIABDAT.MPQ:
     ui_art\\font42y.pcx
 */
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FontSize {
     Size16,
     Size24,
@@ -43,17 +47,44 @@ pub enum FontSize {
     Size42,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FontColor {
     Grey,
     Silver,
     Yellow,
 }
 
+/// Number of glyphs stored in every font
+const GLYPH_COUNT: usize = 256;
+/// Number of glyphs per row of a font atlas
+const ATLAS_COLUMNS: usize = 16;
+/// Glyph used for whitespace and characters that can't be drawn at all
+const GLYPH_SPACE: usize = b' ' as usize;
+/// Default glyph drawn in place of unmapped characters
+const DEFAULT_FALLBACK: char = '?';
+
+/// Characters that have already been reported as unmapped
+/// Keeps the debug log from repeating itself every frame
+static UNMAPPED_CHARS: Mutex<Vec<char>> = Mutex::new(Vec::new());
+
 #[derive(Debug)]
 pub struct Font {
+    metrics: FontMetrics,
+    // All glyphs packed into a single 16x16 grid texture
+    pub atlas: Texture,
+}
+
+/// Glyph sizes and advances of a font, everything needed to lay out text without the atlas
+#[derive(Debug, Clone)]
+pub struct FontMetrics {
     bin: Vec<u8>,
-    pub textures: TextureArray,
+    // Size of a single glyph cell, in pixels
+    glyph_width: usize,
+    glyph_height: usize,
+    // Character drawn in place of ones the font has no glyph for
+    fallback: char,
+    // Should unmapped characters be replaced with similar looking ones?
+    transliterate: bool,
 }
 
 impl Font {
@@ -63,7 +94,7 @@ impl Font {
 
         let bin = {
             let file = archive
-                .get_file(&filename_bin)
+                .get_file_by_key(filename_bin)
                 .context("Failed to get binary file from archive")?;
 
             let mut buf = vec![0x0u8; file.size()];
@@ -71,96 +102,290 @@ impl Font {
             buf
         };
 
-        let textures = {
-            let layers = 256;
+        let (atlas, glyph_width, glyph_height) = {
             let alpha_index = 32;
-            let format = Format::R8g8b8a8_uint;
-            let filtering = Filtering::Nearest;
 
             let file = archive
-                .get_file(&filename_pcx)
+                .get_file_by_key(filename_pcx)
                 .context("Failed to get pcx file from archive")?;
-            let image = Image::read_pcx(&file, Some(alpha_index))?;
+            let image = profile::time(&format!("Decode {}", filename_pcx), || {
+                Image::read_pcx(&file, Some(alpha_index))
+            })?;
 
-            let (width, height) = image.dimensions();
-            let height = height / layers;
+            // Font images store every glyph in a single, vertical strip
+            let (glyph_width, height) = image.dimensions();
+            let glyph_height = height / GLYPH_COUNT;
 
-            TextureArray::new(width, height, layers, format, filtering, &image.pixels)?
+            let atlas = profile::time(&format!("Upload {}", filename_pcx), || {
+                build_atlas(&image, glyph_width, glyph_height).into_texture()
+            })?;
+            (atlas, glyph_width, glyph_height)
         };
 
-        Ok(Self { bin, textures })
+        Ok(Self {
+            metrics: FontMetrics::new(bin, glyph_width, glyph_height),
+            atlas,
+        })
+    }
+
+    /// Set the character drawn in place of characters this font has no glyph for
+    pub fn set_fallback(&mut self, fallback: char) {
+        self.metrics.fallback = fallback;
+    }
+
+    /// Enable or disable replacing unmapped accented characters with their base letter
+    pub fn set_transliterate(&mut self, transliterate: bool) {
+        self.metrics.transliterate = transliterate;
+    }
+
+    /// Draw a laid out string with its top-left corner at a position
+    pub fn draw(
+        &self,
+        batch: &mut Batch,
+        layout: &TextLayout,
+        pos: Vector2<f32>,
+        color: Vector4<f32>,
+    ) {
+        for glyph in &layout.glyphs {
+            let size = Vector2::new(glyph.region.w, glyph.region.h);
+            let center = pos + glyph.offset + size * 0.5;
+            batch.image_region(&self.atlas, glyph.region, Xform2D::position(center), color);
+        }
+    }
+}
+
+impl Deref for Font {
+    type Target = FontMetrics;
+
+    fn deref(&self) -> &FontMetrics {
+        &self.metrics
+    }
+}
+
+impl FontMetrics {
+    /// Create font metrics from a font's bin file and glyph cell size
+    pub fn new(bin: Vec<u8>, glyph_width: usize, glyph_height: usize) -> Self {
+        Self {
+            bin,
+            glyph_width,
+            glyph_height,
+            fallback: DEFAULT_FALLBACK,
+            transliterate: true,
+        }
     }
 
     /// Get the advance, in pixels, of a character in this font
     pub fn get_advance_x(&self, c: char) -> u8 {
-        let c = c as usize;
-        // If the character has a value stored in the bin file
-        if self.bin[c + 2] != 0 {
+        self.get_glyph_advance(self.resolve_glyph(c))
+    }
+
+    /// Get the height, in pixels, of a single glyph in this font
+    pub fn get_glyph_height(&self) -> u32 {
+        self.glyph_height as u32
+    }
+
+    /// Get the height, in pixels, of a line of text in this font
+    pub fn get_line_height(&self) -> u32 {
+        self.bin[1] as u32
+    }
+
+    /// Get the width of a string, if rendered in this font
+    pub fn get_width(&self, string: &str) -> u32 {
+        self.layout(string).width
+    }
+
+    /// Lay out a string in this font
+    /// Layouts are relative to the top-left corner of the text, and can be kept around for static labels
+    pub fn layout(&self, string: &str) -> TextLayout {
+        let line_height = self.get_line_height() as f32;
+
+        let mut glyphs = Vec::with_capacity(string.len());
+        let mut pen = Vector2::new(0.0f32, 0.0f32);
+        let mut width = 0.0f32;
+        for c in string.chars() {
+            if c == '\n' {
+                pen = Vector2::new(0.0, pen.y + line_height);
+                continue;
+            }
+            let index = self.resolve_glyph(c);
+            let advance = self.get_glyph_advance(index) as f32;
+            // Characters without a glyph are whitespace, and only advance the pen
+            if self.has_glyph(index) {
+                glyphs.push(PlacedGlyph {
+                    region: self.glyph_region(index, advance),
+                    offset: pen,
+                });
+            }
+            pen.x += advance;
+            width = width.max(pen.x);
+        }
+        TextLayout {
+            glyphs,
+            width: width as u32,
+            height: pen.y as u32 + self.glyph_height as u32,
+        }
+    }
+
+    /// Get the glyph index used to draw a character
+    /// Characters are mapped through the font's code page, then the transliteration table,
+    /// and finally replaced with the fallback glyph
+    fn resolve_glyph(&self, c: char) -> usize {
+        if c.is_whitespace() {
+            return GLYPH_SPACE;
+        }
+        if let Some(index) = self.lookup_glyph(c) {
+            return index;
+        }
+        if self.transliterate {
+            if let Some(index) = charmap::transliterate(c).and_then(|t| self.lookup_glyph(t)) {
+                return index;
+            }
+        }
+        report_unmapped(c);
+        self.lookup_glyph(self.fallback).unwrap_or(GLYPH_SPACE)
+    }
+
+    /// Get the glyph index of a character, if the font has a glyph for it
+    fn lookup_glyph(&self, c: char) -> Option<usize> {
+        charmap::encode_cp1252(c)
+            .map(|index| index as usize)
+            .filter(|index| self.has_glyph(*index))
+    }
+
+    /// Does this font have a glyph stored at an index?
+    fn has_glyph(&self, index: usize) -> bool {
+        self.bin[index + 2] != 0
+    }
+
+    /// Get the advance, in pixels, of a glyph index
+    fn get_glyph_advance(&self, index: usize) -> u8 {
+        // If the glyph has a value stored in the bin file
+        if self.has_glyph(index) {
             // Return it
-            self.bin[c + 2]
+            self.bin[index + 2]
         } else {
             // Otherwise return the whitespace width
             self.bin[0]
         }
     }
 
-    /// Get the width of a string, if rendered in this font
-    pub fn get_width(&self, string: &str) -> u32 {
-        string.chars().map(|c| self.get_advance_x(c) as u32).sum()
+    /// Get the atlas region of a glyph, trimmed to its advance width
+    fn glyph_region(&self, index: usize, advance: f32) -> Region {
+        let column = index % ATLAS_COLUMNS;
+        let row = index / ATLAS_COLUMNS;
+        Region::new(
+            (column * self.glyph_width) as f32,
+            (row * self.glyph_height) as f32,
+            advance.min(self.glyph_width as f32),
+            self.glyph_height as f32,
+        )
+    }
+}
+
+/// Collection of loaded fonts, looked up by size and color
+#[derive(Debug, Default)]
+pub struct FontSet {
+    fonts: Vec<(FontSize, FontColor, Font)>,
+}
+
+impl FontSet {
+    /// Create a new, empty font set
+    pub fn new() -> Self {
+        Self { fonts: Vec::new() }
     }
 
-    pub fn render<'a>(&'a self, string: &'a str, pos: Vector2<f32>) -> FontStringItr {
-        let chars = string.chars();
-        FontStringItr {
-            font: self,
-            chars,
-            pos,
+    /// Load a font into the set, if it isn't already loaded
+    pub fn load(
+        &mut self,
+        archive: &Archive,
+        size: FontSize,
+        color: FontColor,
+    ) -> anyhow::Result<()> {
+        if self.get(size, color).is_none() {
+            let font = Font::load(archive, size, color)?;
+            self.fonts.push((size, color, font));
         }
+        Ok(())
+    }
+
+    /// Get a loaded font
+    pub fn get(&self, size: FontSize, color: FontColor) -> Option<&Font> {
+        self.fonts
+            .iter()
+            .find(|(s, c, _)| *s == size && *c == color)
+            .map(|(_, _, font)| font)
     }
 }
 
-/// Tuple representing a (layer, position) pair for string rendering
-pub type FontPoint = (u32, Vector2<f32>);
+/// A single glyph placed within a laid out string
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedGlyph {
+    /// Region of the font atlas to draw
+    pub region: Region,
+    /// Offset of the glyph's top-left corner from the top-left of the layout
+    pub offset: Vector2<f32>,
+}
 
-#[derive(Debug)]
-pub struct FontStringItr<'s, 'f> {
-    font: &'f Font,
-    chars: Chars<'s>,
-    pos: Vector2<f32>,
-    // TODO: Bounding boxes
-}
-
-impl Iterator for FontStringItr<'_, '_> {
-    type Item = FontPoint;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // Get the next character, or return none if the character iterator is now empty
-        let c = self.chars.next()?;
-        let (index, advance) = {
-            // Clamp character to ascii range
-            let index = (c as u32).clamp(0, 255);
-            // Invert to layer
-            let index = 255 - index;
-            // TODO: Newline characters
-            // Get the x advancement for this character
-            let advance_x = self.font.get_advance_x(c);
-            (index, Vector2::new(advance_x as f32, 0.0))
-        };
-        // Get the current position to return
-        let current_pos = self.pos.clone();
-        // Advance by the character advancement
-        self.pos += advance;
-        // Return the image index and current position
-        Some((index, current_pos))
+/// Pre-computed layout of a string in a font
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Size of the laid out text, in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Log a character that no font can draw, once per character
+fn report_unmapped(c: char) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if let Ok(mut unmapped) = UNMAPPED_CHARS.lock() {
+        if !unmapped.contains(&c) {
+            unmapped.push(c);
+            eprintln!("No font glyph for character {:?} (U+{:04X})", c, c as u32);
+        }
+    }
+}
+
+/// Repack a vertical strip of glyphs into a square grid atlas
+fn build_atlas(strip: &Image, glyph_width: usize, glyph_height: usize) -> Image {
+    let bpp = 4;
+    let rows = GLYPH_COUNT / ATLAS_COLUMNS;
+    let width = glyph_width * ATLAS_COLUMNS;
+    let height = glyph_height * rows;
+    let mut pixels = vec![0x0u8; width * height * bpp];
+
+    let src_pitch = strip.width * bpp;
+    let dst_pitch = width * bpp;
+    let row_bytes = glyph_width * bpp;
+    for index in 0..GLYPH_COUNT {
+        let column = index % ATLAS_COLUMNS;
+        let row = index / ATLAS_COLUMNS;
+        for y in 0..glyph_height {
+            // NOTE: Image rows are stored bottom first, so count rows up from the end
+            let src_y = strip.height - 1 - (index * glyph_height + y);
+            let dst_y = height - 1 - (row * glyph_height + y);
+
+            let src = src_y * src_pitch;
+            let dst = dst_y * dst_pitch + column * row_bytes;
+            pixels[dst..dst + row_bytes].copy_from_slice(&strip.pixels[src..src + row_bytes]);
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
     }
 }
 
-fn get_font_filenames(size: FontSize, color: FontColor) -> Option<(String, String)> {
+/// Get the archive filenames of a font's bin file and glyph image
+pub fn get_font_filenames(size: FontSize, color: FontColor) -> Option<(AssetKey, AssetKey)> {
     let size: i32 = size.into();
     let color: char = color.into();
 
-    let filename_bin = format!("ui_art\\font{}.bin", size);
-    let filename_pcx = format!("ui_art\\font{}{}.pcx", size, color);
+    let filename_bin = AssetKey::new(format!("ui_art\\font{}.bin", size));
+    let filename_pcx = AssetKey::new(format!("ui_art\\font{}{}.pcx", size, color));
     Some((filename_bin, filename_pcx))
 }
 
@@ -1,11 +1,28 @@
-use std::io::{Cursor, Result};
+use std::fs;
+use std::io::{BufWriter, Cursor, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
 
+#[cfg(feature = "render")]
 use gfx::*;
 use mpq::File;
 
+use super::blit::{blit_with_palette, blit_with_palette_and_transparency};
+use super::pcx_writer::write_pcx;
+
+#[cfg(feature = "render")]
 const IMAGE_TEXTURE_FORMAT: Format = Format::R8g8b8a8_uint;
+#[cfg(feature = "render")]
 const IMAGE_TEXTURE_FILTERING: Filtering = Filtering::Nearest;
 
+/// Resampling filter used to resize images
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Copy the closest pixel, keeping hard pixel edges
+    Nearest,
+    /// Blend the four closest pixels, for smooth thumbnails
+    Bilinear,
+}
+
 #[derive(Debug)]
 pub struct Image {
     pub width: usize,
@@ -19,12 +36,27 @@ impl Image {
         // First, read and decompress the file into a buffer
         let mut file_buf = vec![0x0u8; file.size()];
         file.read(&mut file_buf)?;
+        Self::read_pcx_bytes(&file_buf, transparency_index)
+    }
+
+    /// Decode a PCX image from memory, with an optional transparency
+    /// Both paletted (8-bit) and true color (24-bit) images are supported, so replacement art
+    /// loads whatever its palette mode.
+    /// NOTE: True color images have no palette, so the transparency index only applies to paletted images
+    pub fn read_pcx_bytes(bytes: &[u8], transparency_index: Option<u8>) -> Result<Self> {
         // Create a new PCX file reader over the file buffer
-        let mut reader = pcx::Reader::new(Cursor::new(file_buf))?;
-        // This is here to catch any images without a palette
-        if !reader.is_paletted() {
-            panic!("Non-paletted images are not yet supported");
+        let reader = pcx::Reader::new(Cursor::new(bytes))?;
+        if reader.is_paletted() {
+            Self::read_pcx_paletted(reader, transparency_index)
+        } else {
+            Self::read_pcx_rgb(reader)
         }
+    }
+
+    fn read_pcx_paletted<R: Read>(
+        mut reader: pcx::Reader<R>,
+        transparency_index: Option<u8>,
+    ) -> Result<Self> {
         // Get the dimensions of the image
         let (width, height) = reader.dimensions();
         // Output as an RGBA image (4 bytes per pixel)
@@ -88,10 +120,145 @@ impl Image {
         })
     }
 
+    fn read_pcx_rgb<R: Read>(mut reader: pcx::Reader<R>) -> Result<Self> {
+        let (width, height) = reader.dimensions();
+        let width = width as usize;
+        let height = height as usize;
+        let mut pixels = vec![0x0u8; width * height * 4];
+        let mut row = vec![0x0u8; width * 3];
+        for y in 0..height {
+            reader.next_row_rgb(&mut row)?;
+            // PCX rows go top to bottom, image rows are stored bottom row first
+            let dst_row = (height - 1 - y) * width * 4;
+            let dst = &mut pixels[dst_row..dst_row + width * 4];
+            for (dst, src) in dst.chunks_exact_mut(4).zip(row.chunks_exact(3)) {
+                dst[..3].copy_from_slice(src);
+                dst[3] = 0xFF;
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Write the image as a PNG
+    #[cfg(feature = "render")]
+    pub fn write_png<W: Write>(&self, out: &mut W) -> Result<()> {
+        gfx::write_png(out, self.width, self.height, &self.top_down_pixels())
+    }
+
+    /// Write the image as a PCX, dropping the alpha channel
+    pub fn write_pcx<W: Write>(&self, out: &mut W) -> Result<()> {
+        write_pcx(out, self.width, self.height, &self.top_down_pixels())
+    }
+
+    /// Save the image to disk as a PNG or PCX, depending on the file extension
+    /// Used to dump decoded assets for inspection
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        match extension.as_deref() {
+            #[cfg(feature = "render")]
+            Some("png") => self.write_png(&mut out)?,
+            #[cfg(not(feature = "render"))]
+            Some("png") => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "PNG export requires the render feature",
+                ))
+            }
+            Some("pcx") => self.write_pcx(&mut out)?,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Images can only be saved as .png or .pcx",
+                ))
+            }
+        }
+        out.flush()
+    }
+
     pub fn dimensions(&self) -> (usize, usize) {
         (self.width, self.height)
     }
 
+    /// Resize the image with a resampling filter
+    /// NOTE: Fully transparent pixels are black, so bilinear edges blend towards transparent black
+    pub fn resize(&self, width: usize, height: usize, filter: ResizeFilter) -> Self {
+        let mut pixels = vec![0x0u8; width * height * 4];
+        if self.width > 0 && self.height > 0 {
+            let scale_x = self.width as f32 / width.max(1) as f32;
+            let scale_y = self.height as f32 / height.max(1) as f32;
+            for y in 0..height {
+                for x in 0..width {
+                    // Sample at the center of the output pixel
+                    let src_x = (x as f32 + 0.5) * scale_x - 0.5;
+                    let src_y = (y as f32 + 0.5) * scale_y - 0.5;
+                    let pixel = match filter {
+                        ResizeFilter::Nearest => self.pixel(src_x.round(), src_y.round()),
+                        ResizeFilter::Bilinear => self.sample_bilinear(src_x, src_y),
+                    };
+                    let dst = (y * width + x) * 4;
+                    pixels[dst..dst + 4].copy_from_slice(&pixel);
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Shrink the image to fit within a square, keeping its aspect ratio
+    /// Images that already fit are copied as-is
+    pub fn thumbnail(&self, max_size: usize) -> Self {
+        let largest = self.width.max(self.height);
+        if largest <= max_size {
+            return self.resize(self.width, self.height, ResizeFilter::Nearest);
+        }
+        let width = (self.width * max_size / largest).max(1);
+        let height = (self.height * max_size / largest).max(1);
+        self.resize(width, height, ResizeFilter::Bilinear)
+    }
+
+    /// Get a pixel, clamping the coordinates to the image
+    fn pixel(&self, x: f32, y: f32) -> [u8; 4] {
+        let x = (x.max(0.0) as usize).min(self.width - 1);
+        let y = (y.max(0.0) as usize).min(self.height - 1);
+        let i = (y * self.width + x) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    /// Interpolate between the four pixels around a position
+    fn sample_bilinear(&self, x: f32, y: f32) -> [u8; 4] {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let p00 = self.pixel(x0, y0);
+        let p10 = self.pixel(x0 + 1.0, y0);
+        let p01 = self.pixel(x0, y0 + 1.0);
+        let p11 = self.pixel(x0 + 1.0, y0 + 1.0);
+        let mut pixel = [0x0u8; 4];
+        for c in 0..4 {
+            let top = p00[c] as f32 + (p10[c] as f32 - p00[c] as f32) * tx;
+            let bottom = p01[c] as f32 + (p11[c] as f32 - p01[c] as f32) * tx;
+            pixel[c] = (top + (bottom - top) * ty).round() as u8;
+        }
+        pixel
+    }
+
+    #[cfg(feature = "render")]
     pub fn into_texture(self) -> anyhow::Result<Texture> {
         let (width, height) = self.dimensions();
         Texture::new(
@@ -103,6 +270,7 @@ impl Image {
         )
     }
 
+    #[cfg(feature = "render")]
     pub fn into_texture_array(self, layers: usize) -> anyhow::Result<TextureArray> {
         let (width, height) = self.dimensions();
         let height = height / layers;
@@ -116,4 +284,95 @@ impl Image {
             &self.pixels,
         )
     }
+
+    /// Get the image pixels with the rows in top-down order
+    fn top_down_pixels(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for row in self.pixels.chunks_exact((self.width * 4).max(1)).rev() {
+            pixels.extend_from_slice(row);
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::file::write_pcx_rgb;
+
+    #[test]
+    fn test_read_pcx_rgb() {
+        // 3x2 true color image, top row first
+        #[rustfmt::skip]
+        let rgba = [
+            255, 0, 0, 255,  0, 255, 0, 255,  0, 0, 255, 255,
+            10, 20, 30, 255,  40, 50, 60, 255,  70, 80, 90, 255,
+        ];
+        let mut buf = Vec::new();
+        write_pcx_rgb(&mut buf, 3, 2, &rgba).unwrap();
+
+        let image = Image::read_pcx_bytes(&buf, Some(0)).unwrap();
+        assert_eq!((3, 2), image.dimensions());
+        // Image rows are stored bottom row first
+        assert_eq!(&rgba[12..], &image.pixels[..12]);
+        assert_eq!(&rgba[..12], &image.pixels[12..]);
+    }
+
+    /// Create an opaque grayscale image with a horizontal gradient
+    fn gradient(width: usize, height: usize) -> Image {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for _ in 0..height {
+            for x in 0..width {
+                let value = (x * 255 / (width - 1)) as u8;
+                pixels.extend_from_slice(&[value, value, value, 0xFF]);
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_resize_nearest() {
+        let image = gradient(4, 1);
+        let resized = image.resize(8, 2, ResizeFilter::Nearest);
+        assert_eq!((8, 2), resized.dimensions());
+        // Every source pixel is doubled in both directions
+        let row: Vec<u8> = resized.pixels[..32].chunks(4).map(|p| p[0]).collect();
+        assert_eq!(vec![0, 0, 85, 85, 170, 170, 255, 255], row);
+        assert_eq!(&resized.pixels[..32], &resized.pixels[32..]);
+    }
+
+    #[test]
+    fn test_resize_bilinear() {
+        let image = gradient(256, 4);
+        let resized = image.resize(16, 1, ResizeFilter::Bilinear);
+        let row: Vec<u8> = resized.pixels.chunks(4).map(|p| p[0]).collect();
+        // The gradient is kept, sampled at the center of each output pixel
+        for (x, value) in row.iter().enumerate() {
+            let expected = (x as f32 + 0.5) * 16.0 - 0.5;
+            assert!((*value as f32 - expected).abs() <= 1.0, "{:?}", row);
+        }
+        assert!(resized.pixels.chunks(4).all(|p| p[3] == 0xFF));
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let image = gradient(640, 480);
+        assert_eq!((128, 96), image.thumbnail(128).dimensions());
+        assert_eq!((640, 480), image.thumbnail(1024).dimensions());
+    }
+
+    #[test]
+    #[cfg(not(feature = "render"))]
+    fn test_save_png_headless() {
+        let path = std::env::temp_dir().join("devilution_headless_save.png");
+        let err = gradient(4, 4).save(&path).unwrap_err();
+        let _ = fs::remove_file(&path);
+        assert_eq!(ErrorKind::Unsupported, err.kind());
+        assert_eq!("PNG export requires the render feature", err.to_string());
+    }
 }
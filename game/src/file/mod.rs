@@ -1,5 +1,13 @@
+mod blit;
+// Only used by text rendering, but kept headless so its tests still run
+#[cfg_attr(not(feature = "render"), allow(dead_code))]
+pub(crate) mod charmap;
+#[cfg(feature = "render")]
 mod font;
 mod image;
+mod pcx_writer;
 
+#[cfg(feature = "render")]
 pub use font::*;
 pub use image::*;
+pub use pcx_writer::*;
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::io::{Result, Write};
+
+/// PCX header constants: ZSoft manufacturer id, version 5 (256 color), RLE encoding
+const PCX_MANUFACTURER: u8 = 0x0A;
+const PCX_VERSION: u8 = 5;
+const PCX_ENCODING_RLE: u8 = 1;
+const PCX_HEADER_SIZE: usize = 128;
+/// Marker byte before the 256 color palette at the end of the file
+const PCX_PALETTE_MARKER: u8 = 0x0C;
+/// Longest run a single RLE count byte can store
+const MAX_RUN: usize = 63;
+
+/// Write an RGBA image as a PCX, dropping the alpha channel
+/// Images with at most 256 distinct colors are written paletted, anything else as 24-bit RGB.
+/// Rows are expected top to bottom, 4 bytes per pixel.
+pub fn write_pcx<W: Write>(out: &mut W, width: usize, height: usize, rgba: &[u8]) -> Result<()> {
+    assert_eq!(rgba.len(), width * height * 4);
+
+    // Try to build a palette out of the image colors
+    let mut palette = [0x0u8; 256 * 3];
+    let mut colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(width * height);
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let next_index = colors.len();
+        let index = match colors.get(&color) {
+            Some(index) => *index,
+            None if next_index < 256 => {
+                palette[next_index * 3..next_index * 3 + 3].copy_from_slice(&color);
+                colors.insert(color, next_index as u8);
+                next_index as u8
+            }
+            // Too many colors for a palette
+            None => return write_pcx_rgb(out, width, height, rgba),
+        };
+        indices.push(index);
+    }
+    write_pcx_paletted(out, width, height, &indices, &palette)
+}
+
+/// Write an 8-bit paletted image as a PCX
+/// Rows are expected top to bottom, 1 palette index per pixel.
+pub fn write_pcx_paletted<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    indices: &[u8],
+    palette: &[u8; 256 * 3],
+) -> Result<()> {
+    assert_eq!(indices.len(), width * height);
+
+    let pitch = write_header(out, width, height, 1)?;
+    let mut line = vec![0x0u8; pitch];
+    for row in indices.chunks_exact(width.max(1)).take(height) {
+        line[..width].copy_from_slice(row);
+        write_rle(out, &line)?;
+    }
+    out.write_all(&[PCX_PALETTE_MARKER])?;
+    out.write_all(palette)
+}
+
+/// Write an RGBA image as a 24-bit (3 plane) PCX, dropping the alpha channel
+/// Rows are expected top to bottom, 4 bytes per pixel.
+pub fn write_pcx_rgb<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+) -> Result<()> {
+    assert_eq!(rgba.len(), width * height * 4);
+
+    let pitch = write_header(out, width, height, 3)?;
+    let mut line = vec![0x0u8; pitch];
+    for row in rgba.chunks_exact((width * 4).max(1)).take(height) {
+        // Each scanline stores every red value, then every green, then every blue
+        for plane in 0..3 {
+            for (x, pixel) in row.chunks_exact(4).enumerate() {
+                line[x] = pixel[plane];
+            }
+            write_rle(out, &line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the PCX header, returning the (padded) number of bytes in each scanline plane
+fn write_header<W: Write>(out: &mut W, width: usize, height: usize, planes: u8) -> Result<usize> {
+    // Scanlines are always an even number of bytes
+    let pitch = (width + 1) & !1;
+
+    let mut header = [0x0u8; PCX_HEADER_SIZE];
+    header[0] = PCX_MANUFACTURER;
+    header[1] = PCX_VERSION;
+    header[2] = PCX_ENCODING_RLE;
+    header[3] = 8;
+    // Image bounds, inclusive
+    header[8..10].copy_from_slice(&(width.saturating_sub(1) as u16).to_le_bytes());
+    header[10..12].copy_from_slice(&(height.saturating_sub(1) as u16).to_le_bytes());
+    // DPI, which nothing reads
+    header[12..14].copy_from_slice(&72u16.to_le_bytes());
+    header[14..16].copy_from_slice(&72u16.to_le_bytes());
+    header[65] = planes;
+    header[66..68].copy_from_slice(&(pitch as u16).to_le_bytes());
+    // Palette info: color
+    header[68..70].copy_from_slice(&1u16.to_le_bytes());
+    out.write_all(&header)?;
+    Ok(pitch)
+}
+
+/// Run-length encode a single scanline plane
+fn write_rle<W: Write>(out: &mut W, line: &[u8]) -> Result<()> {
+    let mut i = 0;
+    while i < line.len() {
+        let value = line[i];
+        let mut run = 1;
+        while i + run < line.len() && line[i + run] == value && run < MAX_RUN {
+            run += 1;
+        }
+        // Values with the top two bits set would read as a count byte, so always get one
+        if run > 1 || value >= 0xC0 {
+            out.write_all(&[0xC0 | run as u8, value])?;
+        } else {
+            out.write_all(&[value])?;
+        }
+        i += run;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_pcx_paletted() {
+        // 3x2 image, which exercises scanline padding, runs and escaped values
+        let (width, height) = (3, 2);
+        #[rustfmt::skip]
+        let rgba = [
+            10, 20, 30, 255,  10, 20, 30, 255,  200, 200, 200, 255,
+            200, 200, 200, 255,  1, 2, 3, 255,  1, 2, 3, 255,
+        ];
+        let mut buf = Vec::new();
+        write_pcx(&mut buf, width, height, &rgba).unwrap();
+
+        let mut reader = pcx::Reader::new(Cursor::new(buf)).unwrap();
+        assert!(reader.is_paletted());
+        assert_eq!((3, 2), reader.dimensions());
+        let mut rows = [[0x0u8; 3]; 2];
+        for row in rows.iter_mut() {
+            reader.next_row_paletted(row).unwrap();
+        }
+        let mut palette = [0x0u8; 256 * 3];
+        reader.read_palette(&mut palette).unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, index) in row.iter().enumerate() {
+                let expected = &rgba[(y * width + x) * 4..][..3];
+                let index = *index as usize;
+                assert_eq!(expected, &palette[index * 3..index * 3 + 3]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_pcx_rgb() {
+        // 19x16 image with 286 distinct colors, too many for a palette
+        // The last row is a single color, to exercise runs across the planes
+        let (width, height) = (19, 16);
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for i in 0..width * height {
+            let color = if i / width == height - 1 {
+                [0xC8, 0xC8, 0xC8]
+            } else {
+                [i as u8, (i / 256) as u8 * 0xE0, 0xFF - (i % 7) as u8]
+            };
+            rgba.extend_from_slice(&color);
+            rgba.push(0xFF);
+        }
+        let mut buf = Vec::new();
+        write_pcx(&mut buf, width, height, &rgba).unwrap();
+
+        let mut reader = pcx::Reader::new(Cursor::new(buf)).unwrap();
+        assert!(!reader.is_paletted());
+        assert_eq!((19, 16), reader.dimensions());
+        let mut row = [0x0u8; 19 * 3];
+        for y in 0..height {
+            reader.next_row_rgb(&mut row).unwrap();
+            for (x, pixel) in row.chunks_exact(3).enumerate() {
+                let expected = &rgba[(y * width + x) * 4..][..3];
+                assert_eq!(expected, pixel);
+            }
+        }
+    }
+}
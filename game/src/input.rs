@@ -0,0 +1,397 @@
+use glfw::{Action, Key, Modifiers, MouseButton};
+
+use crate::msg::{MsgBus, MsgData};
+
+/// Time a key must be held before it starts repeating, in seconds
+pub const KEY_REPEAT_DELAY: f64 = 0.4;
+/// Time between repeats of a held key, in seconds
+pub const KEY_REPEAT_INTERVAL: f64 = 0.05;
+/// Longest time between two clicks of a double-click, in seconds
+pub const DOUBLE_CLICK_TIME: f64 = 0.5;
+/// Furthest the cursor can move between two clicks of a double-click, in virtual pixels
+pub const DOUBLE_CLICK_DISTANCE: f32 = 4.0;
+/// Distance the cursor must move with a button held to start a drag, in virtual pixels
+pub const DRAG_THRESHOLD: f32 = 4.0;
+
+/// A key that is currently held down
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    key: Key,
+    // Time the key has been held for, in seconds
+    time: f64,
+    // Hold time at which the key next repeats
+    next_repeat: f64,
+    // Did the key repeat during the current tick?
+    repeated: bool,
+    // Was the key pressed since the last update?
+    fresh: bool,
+}
+
+/// A mouse button press that may become a click or a drag
+#[derive(Debug, Clone, Copy)]
+struct ButtonPress {
+    button: MouseButton,
+    mods: Modifiers,
+    pos: (f32, f32),
+    dragging: bool,
+}
+
+/// The last click, kept to detect double-clicks
+#[derive(Debug, Clone, Copy)]
+struct LastClick {
+    button: MouseButton,
+    pos: (f32, f32),
+    time: f64,
+}
+
+/// Held key and mouse state, built from the input messages on the message bus
+/// The message bus only carries discrete press/release events, this tracks which keys are held
+/// (for movement) and when they repeat (for menus).
+/// Mouse gestures (clicks, double-clicks and drags) are recognized here as well, and sent back
+/// to the message bus as higher-level messages.
+/// NOTE: Press/release/repeat queries describe the current tick, and are reset by `update`
+#[derive(Debug, Clone)]
+pub struct InputState {
+    held: Vec<HeldKey>,
+    held_buttons: Vec<MouseButton>,
+    // Keys pressed/released since the last update, and during the current tick
+    pending_pressed: Vec<Key>,
+    pending_released: Vec<Key>,
+    pressed: Vec<Key>,
+    released: Vec<Key>,
+    // Key repeat timing, in seconds
+    repeat_delay: f64,
+    repeat_interval: f64,
+    // Time since the input state was created, in seconds
+    time: f64,
+    // Mouse gesture state
+    cursor: (f32, f32),
+    press: Option<ButtonPress>,
+    last_click: Option<LastClick>,
+}
+
+impl InputState {
+    /// Create a new input state, using the default key repeat timing
+    pub fn new() -> Self {
+        Self::with_repeat(KEY_REPEAT_DELAY, KEY_REPEAT_INTERVAL)
+    }
+
+    /// Create a new input state with custom key repeat timing, in seconds
+    pub fn with_repeat(delay: f64, interval: f64) -> Self {
+        Self {
+            held: Vec::new(),
+            held_buttons: Vec::new(),
+            pending_pressed: Vec::new(),
+            pending_released: Vec::new(),
+            pressed: Vec::new(),
+            released: Vec::new(),
+            repeat_delay: delay,
+            repeat_interval: interval.max(f64::EPSILON),
+            time: 0.0,
+            cursor: (0.0, 0.0),
+            press: None,
+            last_click: None,
+        }
+    }
+
+    /// Apply an input message, pushing any recognized mouse gestures to the message bus
+    /// NOTE: OS key repeat events are ignored, repeats are timed by `update` instead
+    pub fn handle_msg(&mut self, msg: &MsgData, msg_bus: &mut MsgBus) {
+        match *msg {
+            MsgData::Key(key, Action::Press, _) => {
+                if !self.is_held(key) {
+                    self.held.push(HeldKey {
+                        key,
+                        time: 0.0,
+                        next_repeat: self.repeat_delay,
+                        repeated: false,
+                        fresh: true,
+                    });
+                }
+                self.pending_pressed.push(key);
+            }
+            MsgData::Key(key, Action::Release, _) => {
+                self.held.retain(|held| held.key != key);
+                self.pending_released.push(key);
+            }
+            MsgData::MouseMove(pos) => self.mouse_moved(pos, msg_bus),
+            MsgData::MouseButton(button, Action::Press, mods, pos) => {
+                self.cursor = pos;
+                if !self.held_buttons.contains(&button) {
+                    self.held_buttons.push(button);
+                }
+                // Only one button at a time can click or drag
+                if self.press.is_none() {
+                    self.press = Some(ButtonPress {
+                        button,
+                        mods,
+                        pos,
+                        dragging: false,
+                    });
+                }
+            }
+            MsgData::MouseButton(button, Action::Release, _, pos) => {
+                self.cursor = pos;
+                self.held_buttons.retain(|held| *held != button);
+                match self.press {
+                    Some(press) if press.button == button => {
+                        self.press = None;
+                        self.button_released(press, pos, msg_bus);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Start a new tick, advancing the hold timers by a delta time
+    pub fn update(&mut self, delta: f64) {
+        self.time += delta;
+        self.pressed = std::mem::take(&mut self.pending_pressed);
+        self.released = std::mem::take(&mut self.pending_released);
+        for held in &mut self.held {
+            held.repeated = false;
+            // Keys start timing from the tick they were pressed on
+            if held.fresh {
+                held.fresh = false;
+                continue;
+            }
+            held.time += delta;
+            if held.time >= held.next_repeat {
+                held.repeated = true;
+                // Never repeat more than once per tick, even after a long stall
+                while held.next_repeat <= held.time {
+                    held.next_repeat += self.repeat_interval;
+                }
+            }
+        }
+    }
+
+    /// Release every held key and mouse button, e.g. when the window loses focus
+    /// NOTE: Any click or drag in progress is cancelled
+    pub fn release_all(&mut self) {
+        let keys: Vec<Key> = self.held.drain(..).map(|held| held.key).collect();
+        self.pending_released.extend(keys);
+        self.held_buttons.clear();
+        self.press = None;
+    }
+
+    /// Get the last known cursor position, in virtual screen coordinates
+    pub fn cursor(&self) -> (f32, f32) {
+        self.cursor
+    }
+
+    /// Check if a mouse button is currently held down
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.held_buttons.contains(&button)
+    }
+
+    /// Check if a mouse drag is in progress
+    pub fn is_dragging(&self) -> bool {
+        matches!(self.press, Some(ButtonPress { dragging: true, .. }))
+    }
+
+    /// Check if a key is currently held down
+    pub fn is_held(&self, key: Key) -> bool {
+        self.held.iter().any(|held| held.key == key)
+    }
+
+    /// Get how long a key has been held down for, in seconds
+    pub fn held_time(&self, key: Key) -> Option<f64> {
+        self.held
+            .iter()
+            .find(|held| held.key == key)
+            .map(|held| held.time)
+    }
+
+    /// Check if a key was pressed during the current tick
+    pub fn was_pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// Check if a key was released during the current tick
+    pub fn was_released(&self, key: Key) -> bool {
+        self.released.contains(&key)
+    }
+
+    /// Check if a held key repeated during the current tick
+    pub fn was_repeated(&self, key: Key) -> bool {
+        self.held
+            .iter()
+            .any(|held| held.key == key && held.repeated)
+    }
+
+    /// Check if a key was pressed or repeated during the current tick
+    /// Used for menu navigation, where holding a key keeps moving the selection
+    pub fn is_triggered(&self, key: Key) -> bool {
+        self.was_pressed(key) || self.was_repeated(key)
+    }
+
+    /// Track the cursor, starting or continuing a drag
+    fn mouse_moved(&mut self, pos: (f32, f32), msg_bus: &mut MsgBus) {
+        self.cursor = pos;
+        if let Some(press) = &mut self.press {
+            if !press.dragging && distance(press.pos, pos) >= DRAG_THRESHOLD {
+                press.dragging = true;
+                msg_bus.push(MsgData::DragStart(press.button, press.mods, press.pos));
+            }
+            if press.dragging {
+                msg_bus.push(MsgData::Drag(press.button, pos));
+            }
+        }
+    }
+
+    /// Finish a button press as either a click or the end of a drag
+    fn button_released(&mut self, press: ButtonPress, pos: (f32, f32), msg_bus: &mut MsgBus) {
+        if press.dragging {
+            msg_bus.push(MsgData::DragEnd(press.button, pos));
+            return;
+        }
+        msg_bus.push(MsgData::Click(press.button, press.mods, press.pos));
+        let is_double = match self.last_click {
+            Some(last) => {
+                last.button == press.button
+                    && self.time - last.time <= DOUBLE_CLICK_TIME
+                    && distance(last.pos, press.pos) <= DOUBLE_CLICK_DISTANCE
+            }
+            None => false,
+        };
+        if is_double {
+            // A third click starts a new double-click instead of continuing this one
+            self.last_click = None;
+            msg_bus.push(MsgData::DoubleClick(press.button, press.mods, press.pos));
+        } else {
+            self.last_click = Some(LastClick {
+                button: press.button,
+                pos: press.pos,
+                time: self.time,
+            });
+        }
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Distance between two points, in virtual pixels
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, action: Action) -> MsgData {
+        MsgData::Key(key, action, Modifiers::empty())
+    }
+
+    fn button(action: Action, pos: (f32, f32)) -> MsgData {
+        MsgData::MouseButton(MouseButton::Button1, action, Modifiers::empty(), pos)
+    }
+
+    fn drain(msg_bus: &mut MsgBus) -> Vec<MsgData> {
+        std::iter::from_fn(|| msg_bus.pop())
+            .map(|msg| msg.data)
+            .collect()
+    }
+
+    #[test]
+    fn test_press_and_release() {
+        let mut msg_bus = MsgBus::new(0);
+        let mut input = InputState::new();
+        input.handle_msg(&key(Key::W, Action::Press), &mut msg_bus);
+        input.update(0.1);
+        assert!(input.is_held(Key::W));
+        assert!(input.was_pressed(Key::W));
+        assert_eq!(Some(0.0), input.held_time(Key::W));
+
+        input.update(0.1);
+        assert!(input.is_held(Key::W));
+        assert!(!input.was_pressed(Key::W));
+
+        input.handle_msg(&key(Key::W, Action::Release), &mut msg_bus);
+        input.update(0.1);
+        assert!(!input.is_held(Key::W));
+        assert!(input.was_released(Key::W));
+    }
+
+    #[test]
+    fn test_key_repeat() {
+        let mut msg_bus = MsgBus::new(0);
+        let mut input = InputState::with_repeat(0.5, 0.25);
+        input.handle_msg(&key(Key::Down, Action::Press), &mut msg_bus);
+        // OS repeats are ignored
+        input.handle_msg(&key(Key::Down, Action::Repeat), &mut msg_bus);
+
+        let mut triggers = 0;
+        for _ in 0..10 {
+            input.update(0.125);
+            if input.is_triggered(Key::Down) {
+                triggers += 1;
+            }
+        }
+        // One press, then repeats at 0.5, 0.75 and 1.0 seconds
+        assert_eq!(4, triggers);
+    }
+
+    #[test]
+    fn test_click_and_double_click() {
+        use MouseButton::Button1;
+
+        let mut msg_bus = MsgBus::new(0);
+        let mut input = InputState::new();
+        let none = Modifiers::empty();
+        input.handle_msg(&button(Action::Press, (10.0, 10.0)), &mut msg_bus);
+        input.handle_msg(&button(Action::Release, (11.0, 10.0)), &mut msg_bus);
+        input.update(0.1);
+        input.handle_msg(&button(Action::Press, (12.0, 11.0)), &mut msg_bus);
+        input.handle_msg(&button(Action::Release, (12.0, 11.0)), &mut msg_bus);
+        assert_eq!(
+            vec![
+                MsgData::Click(Button1, none, (10.0, 10.0)),
+                MsgData::Click(Button1, none, (12.0, 11.0)),
+                MsgData::DoubleClick(Button1, none, (12.0, 11.0)),
+            ],
+            drain(&mut msg_bus)
+        );
+
+        // Too slow to be a double-click
+        input.update(1.0);
+        input.handle_msg(&button(Action::Press, (12.0, 11.0)), &mut msg_bus);
+        input.handle_msg(&button(Action::Release, (12.0, 11.0)), &mut msg_bus);
+        assert_eq!(
+            vec![MsgData::Click(Button1, none, (12.0, 11.0))],
+            drain(&mut msg_bus)
+        );
+    }
+
+    #[test]
+    fn test_drag() {
+        use MouseButton::Button1;
+
+        let mut msg_bus = MsgBus::new(0);
+        let mut input = InputState::new();
+        input.handle_msg(&button(Action::Press, (10.0, 10.0)), &mut msg_bus);
+        // Small movements don't start a drag
+        input.handle_msg(&MsgData::MouseMove((11.0, 11.0)), &mut msg_bus);
+        assert!(!input.is_dragging());
+        input.handle_msg(&MsgData::MouseMove((20.0, 10.0)), &mut msg_bus);
+        assert!(input.is_dragging());
+        input.handle_msg(&button(Action::Release, (25.0, 10.0)), &mut msg_bus);
+        assert_eq!(
+            vec![
+                MsgData::DragStart(Button1, Modifiers::empty(), (10.0, 10.0)),
+                MsgData::Drag(Button1, (20.0, 10.0)),
+                MsgData::DragEnd(Button1, (25.0, 10.0)),
+            ],
+            drain(&mut msg_bus)
+        );
+        assert!(!input.is_button_held(Button1));
+    }
+}
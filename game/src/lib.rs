@@ -1,8 +1,90 @@
+//! DevilutionRS engine library
+//!
+//! The `game` binary is a thin shell around this crate, so tools and tests can use the same pieces:
+//! - `mpq`: Archive access (re-exported from the `mpq` crate)
+//! - `gfx`: Batch renderer and GPU resources (re-exported from the `gfx` crate)
+//! - `file`: Decoders for the archive file formats (images, fonts)
+//! - `audio`: PCM conversion, resampling and sound caching
+//! - `anim`, `clock`, `input`, `msg`, `text`: Game loop building blocks
+//! - `coords`, `direction`: Map geometry and entity facing
+//! - `feedback`: Camera shake and damage flashes
+//! - `profile`: Timing of load phases, for `--profile-startup`
+//! - `screen`: The game screens and the transitions between them
+//!
+//! Depending on this crate should be enough, the re-exports keep the engine crate versions in sync.
+//!
+//! Everything touching GL or GLFW (`gfx`, `input`, `msg`, `screen`, `text`, fonts, textures and
+//! sprite drawing) is behind the default `render` feature. Build with `--no-default-features`
+//! to use the rest headless.
+
+#[cfg(feature = "render")]
+pub use gfx;
+pub use mpq;
+
 pub mod anim;
+pub mod audio;
+pub mod clock;
+pub mod coords;
+pub mod direction;
+pub mod feedback;
 pub mod file;
+#[cfg(feature = "render")]
+pub mod input;
+#[cfg(feature = "render")]
 pub mod msg;
+pub mod profile;
+#[cfg(feature = "render")]
 pub mod screen;
+#[cfg(feature = "render")]
+pub mod text;
 
 /// Game rendering constants
+/// Screens and UI are laid out at this size, whatever the render size
 pub const RENDER_WIDTH: u32 = 640;
 pub const RENDER_HEIGHT: u32 = 480;
+/// Render width in widescreen mode
+pub const WIDESCREEN_RENDER_WIDTH: u32 = 960;
+
+/// Virtual resolution the game is rendered at
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RenderSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenderSize {
+    /// The original 640x480 resolution
+    pub const STANDARD: Self = Self {
+        width: RENDER_WIDTH,
+        height: RENDER_HEIGHT,
+    };
+    /// A wider view of the world, with the UI kept at its original layout in the middle
+    pub const WIDESCREEN: Self = Self {
+        width: WIDESCREEN_RENDER_WIDTH,
+        height: RENDER_HEIGHT,
+    };
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Get the offset of the 640x480 UI area, which is centered in the render area
+    pub fn ui_offset(&self) -> cgmath::Vector2<f32> {
+        cgmath::Vector2::new(
+            (self.width as f32 - RENDER_WIDTH as f32) * 0.5,
+            (self.height as f32 - RENDER_HEIGHT as f32) * 0.5,
+        )
+    }
+}
+
+impl From<RenderSize> for (u32, u32) {
+    fn from(size: RenderSize) -> Self {
+        (size.width, size.height)
+    }
+}
+
+impl Default for RenderSize {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
@@ -1,36 +1,81 @@
+use std::path::Path;
+
 use glfw::{OpenGlProfileHint, Window, WindowEvent, WindowHint};
 
 use cgmath::*;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 
-use mpq::Archive;
+use mpq::{expand_pattern, AccessTrace, Archive};
 
 use gfx::*;
 
+use game::clock::*;
+use game::input::*;
 use game::msg::*;
 use game::screen::*;
 use game::*;
 
 // Window constants
 pub const TITLE: &str = "Diablo";
-pub const SCREEN_WIDTH: u32 = RENDER_WIDTH;
-pub const SCREEN_HEIGHT: u32 = RENDER_HEIGHT;
 // Rendering constants
-// TODO: Tune these as needed
-pub const MAX_INDICES: usize = 1024;
-pub const MAX_VERTICES: usize = 1024;
+// Sized for the largest batch 16-bit indices can address (~10k sprites at 6 vertices/indices each),
+// which the stress screen fills in well under 4 MiB of buffers
+pub const MAX_INDICES: usize = MAX_BATCH_VERTICES;
+pub const MAX_VERTICES: usize = MAX_BATCH_VERTICES;
 pub const MAX_MESSAGES: usize = 1024;
+// Debug constants
+pub const CAPTURE_DIR: &str = "capture";
+// Length of recorded clips in seconds, and their frame rate
+pub const CLIP_DURATION: f64 = 10.0;
+pub const CLIP_FPS: f64 = 15.0;
 
 fn main() -> anyhow::Result<()> {
     use glfw::Context;
 
+    let args: Vec<String> = std::env::args().collect();
+    // Time each phase of startup, printing a breakdown once the first screen is loaded
+    let profile_startup = args.iter().any(|arg| arg == "--profile-startup");
+    if profile_startup {
+        profile::enable();
+    }
+
     // Open the Diablo MPQ archive
     // TODO: Hellfire support?
-    let diablo_mpq = Archive::open("data/DIABDAT.MPQ")?;
+    let mut diablo_mpq = profile::time("Open archive (header and table decryption)", || {
+        Archive::open("data/DIABDAT.MPQ")
+    })?;
+    // Log every archive file read to a CSV file, to tune the prefetch manifests and cache sizes
+    if let Some(index) = args.iter().position(|arg| arg == "--trace-archive") {
+        let trace_path = args
+            .get(index + 1)
+            .context("--trace-archive needs a CSV file path")?;
+        let trace = AccessTrace::create(trace_path)
+            .with_context(|| format!("Failed to create archive trace {}", trace_path))?;
+        diablo_mpq.set_access_trace(Some(trace));
+    }
+    // Check that every file the screens need is present, without starting the game
+    if args.iter().any(|arg| arg == "--verify-assets") {
+        return verify_assets(&diablo_mpq);
+    }
+    // Resolve archive filenames from a file of name patterns, printing the result as a listfile
+    if let Some(index) = args.iter().position(|arg| arg == "--resolve-names") {
+        let patterns = args
+            .get(index + 1)
+            .context("--resolve-names needs a name pattern file")?;
+        return resolve_names(&diablo_mpq, patterns);
+    }
+
+    // Widescreen renders a wider area, keeping the UI layout in the middle
+    let render_size = if args.iter().any(|arg| arg == "--widescreen") {
+        RenderSize::WIDESCREEN
+    } else {
+        RenderSize::STANDARD
+    };
 
     // Initalize GLFW
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).context("Failed to initialize GLFW3")?;
+    let mut glfw = profile::time("Initialize GLFW", || glfw::init(glfw::FAIL_ON_ERRORS))
+        .context("Failed to initialize GLFW3")?;
     // Set some window hints to get an OpenGL context
     glfw.window_hint(WindowHint::Resizable(true));
     glfw.window_hint(WindowHint::SRgbCapable(true));
@@ -40,150 +85,348 @@ fn main() -> anyhow::Result<()> {
     glfw.window_hint(WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
     glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
     // Create the window and event handler
-    let (mut window, events) = glfw
-        .create_window(
-            SCREEN_WIDTH,
-            SCREEN_HEIGHT,
+    let (mut window, events) = profile::time("Create window", || {
+        glfw.create_window(
+            render_size.width,
+            render_size.height,
             TITLE,
             glfw::WindowMode::Windowed,
         )
-        .context("Failed to create GLFW window")?;
+    })
+    .context("Failed to create GLFW window")?;
     // Bind the window
-    window.set_aspect_ratio(RENDER_WIDTH, RENDER_HEIGHT);
+    window.set_aspect_ratio(render_size.width, render_size.height);
     window.set_key_polling(true);
+    window.set_char_polling(true);
+    window.set_focus_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_refresh_polling(true);
     window.make_current();
 
     // Load the OpenGL function pointers
     gl::load_with(|s| glfw.get_proc_address_raw(s));
 
     // Create a geometry-batching renderer
-    let mut batch = Batch::new(MAX_VERTICES, MAX_INDICES);
+    let mut batch = profile::time("Create batch", || Batch::new(MAX_VERTICES, MAX_INDICES));
+    // Snap textured art to the virtual pixel grid, so it doesn't shimmer at non-integer scales
+    batch.set_pixel_snap(Material::Textured, true);
+    batch.set_pixel_snap(Material::LayeredTexture, true);
+    batch.set_pixel_snap(Material::Outline, true);
     // Initialize the rendering materials
-    let materials = MaterialMap::new()?;
+    let materials = profile::time("Compile materials", MaterialMap::new)?;
 
     // Initialize the message bus
     let mut msg_bus = MsgBus::new(MAX_MESSAGES);
-    // Initialize at the title screen
+    // Initialize the held key state
+    let mut input = InputState::new();
+    // Initialize at the title screen, or at the renderer stress test if requested
     // TODO: Intro video
-    let mut screen: Box<dyn GameScreen> = GameScreenName::Title.init(&diablo_mpq)?;
-
-    let mut frame_timer = 0.0;
-    let frame_rate = 1.0 / 60.0;
+    let first_screen = if args.iter().any(|arg| arg == "--stress") {
+        GameScreenName::Stress
+    } else {
+        GameScreenName::Title
+    };
+    let mut screen: Box<dyn GameScreen> =
+        profile::time(&format!("Load {:?} screen", first_screen), || {
+            first_screen.init(&diablo_mpq)
+        })?;
+    if profile_startup {
+        profile::print_phases(&profile::finish());
+    }
 
-    let mut last_time = glfw.get_time();
+    // Initialize the fixed-step game clock
+    let mut clock = GameClock::new(1.0 / 60.0);
+    // Frames are only redrawn when something on screen could have changed, unless asked to
+    // redraw every frame (e.g. to profile the renderer)
+    let always_redraw = args.iter().any(|arg| arg == "--always-redraw");
+    let mut redraw = true;
+    let mut last_window_size = (0, 0);
+    // Keep the last few seconds of frames at half the render size, to save as a clip with F5
+    let mut clip_recorder = args.iter().any(|arg| arg == "--record-clips").then(|| {
+        ClipRecorder::new(
+            render_size.width as usize / 2,
+            render_size.height as usize / 2,
+            CLIP_DURATION,
+            CLIP_FPS,
+        )
+    });
+    // Start timing from here, so loading doesn't count as elapsed frame time
+    clock.update(&glfw);
     while !window.should_close() {
-        // Calculate the delta time from last frame
-        let now_time = glfw.get_time();
-        let delta = now_time - last_time;
-        last_time = now_time;
-
-        // Update the current screen at a fixed rate
-        frame_timer += delta;
-        while frame_timer >= frame_rate {
+        // Update the current screen at a fixed rate, with the time since the last frame
+        clock.update(&glfw);
+        let mut ticked = false;
+        while clock.tick() {
+            ticked = true;
+            let step = clock.step();
             // Update the message bus
-            msg_bus.update(frame_rate);
+            msg_bus.update(step);
+            // Start the input tick
+            input.update(step);
             // Update the game and check if a screen was returned to transition to
-            if let Some(next_screen) = screen.update(&mut msg_bus, frame_rate) {
+            if let Some(next_screen) = screen.update(&mut msg_bus, &input, step) {
                 // Initialize the new screen
                 screen = next_screen.init(&diablo_mpq)?;
+                redraw = true;
             }
-            // Subtract the used time from the frame timer
-            frame_timer -= frame_rate;
         }
 
-        // Get the current window size and the rendering aspect ratio
+        // Get the current framebuffer size
         let window_size = window.get_framebuffer_size();
-        let aspect_ratio = RENDER_WIDTH as f32 / RENDER_HEIGHT as f32;
-        // Calculate the viewport and projection matrix
-        let viewport = Viewport::from_window(aspect_ratio, window_size);
-        let projection = {
-            let scale_x = window_size.0 as f32 / RENDER_WIDTH as f32;
-            let scale_y = window_size.1 as f32 / RENDER_HEIGHT as f32;
-            let scale = Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.0);
-            let ortho = ortho(
-                0.0,
-                window_size.0 as f32,
-                window_size.1 as f32,
-                0.0,
-                -1.0,
-                1.0,
-            );
-            ortho * scale
-        };
-        // Clear the batch
-        batch.clear();
-        {
-            // Render the current screen
-            screen.render(&mut batch);
-        }
-        // Flush the batch to the GPU
-        batch.flush(projection);
+        // Static screens only change on input, and nothing animates while the game is paused
+        redraw |=
+            always_redraw || window_size != last_window_size || (ticked && screen.is_animating());
+        last_window_size = window_size;
+        if redraw {
+            // Calculate the viewport and projection matrix
+            let Projection {
+                matrix, viewport, ..
+            } = Projection::pixel_perfect(render_size.into(), window_size);
+            let projection = {
+                // Every screen is currently laid out as UI, so it's kept in the 640x480 area
+                // TODO: Draw the world across the whole render area once levels are rendered
+                let ui_offset = Matrix4::from_translation(render_size.ui_offset().extend(0.0));
+                matrix * ui_offset
+            };
+            // Clear the batch
+            batch.clear();
+            {
+                // Dim the screen while the simulation is paused
+                if clock.is_paused() {
+                    batch.set_effect(ColorEffect::dimmed(0.5));
+                }
+                // Render the current screen
+                screen.render(&mut batch);
+                batch.set_effect(ColorEffect::NONE);
+            }
+            // Flush the batch to the GPU
+            // TODO: Give the world its own projection once there is a camera
+            batch.set_projection(UniformSet::World, projection);
+            batch.set_projection(UniformSet::Screen, projection);
+            batch.flush();
 
-        // Bind some rendering state to the GPU and clear the screen
-        unsafe {
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::Disable(gl::CULL_FACE);
+            // Bind some rendering state to the GPU and clear the screen
+            unsafe {
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Disable(gl::CULL_FACE);
 
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
 
-            gl::Enable(gl::BLEND);
-            gl::BlendEquation(gl::FUNC_ADD);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                gl::Enable(gl::BLEND);
+                gl::BlendEquation(gl::FUNC_ADD);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
 
-            gl::Viewport(viewport.x, viewport.y, viewport.w, viewport.h);
-            gl::Scissor(viewport.x, viewport.y, viewport.w, viewport.h);
+                gl::Viewport(viewport.x, viewport.y, viewport.w, viewport.h);
+                gl::Scissor(viewport.x, viewport.y, viewport.w, viewport.h);
 
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            // Render the batch to the screen
+            batch.render(&materials);
+            // Dump any completed frame capture to disk
+            if let Some(capture) = batch.take_capture() {
+                if let Err(err) = capture.dump(CAPTURE_DIR) {
+                    eprintln!("Failed to dump frame capture: {}", err);
+                }
+            }
+            // Record the frame before it's swapped out of the back buffer
+            if let Some(recorder) = clip_recorder.as_mut() {
+                recorder.record(glfw.get_time(), &viewport);
+            }
+            // Swap the window buffers and poll the events
+            window.swap_buffers();
+            glfw.poll_events();
+            redraw = false;
+        } else {
+            // The last frame is still on screen, so sleep until an event or the next tick
+            glfw.wait_events_timeout(clock.step());
         }
-        // Render the batch to the screen
-        batch.render(&materials);
-        // Swap the window buffers and poll the events
-        window.swap_buffers();
-        glfw.poll_events();
         // Handle each event in the loop
         for (_, event) in glfw::flush_messages(&events) {
-            handle_event(&mut window, &event, &mut msg_bus);
+            // Any event could change what's on screen
+            redraw = true;
+            let debug = DebugState {
+                clock: &mut clock,
+                batch: &mut batch,
+                clip_recorder: clip_recorder.as_ref(),
+                render_size,
+            };
+            handle_event(&mut window, &event, debug, &mut msg_bus, &mut input);
         }
     }
+    // Free the GPU resources while the context is still alive, then check for leaks
+    drop(screen);
+    drop(batch);
+    report_gpu_leaks();
     Ok(())
 }
 
-fn handle_event(window: &mut Window, event: &WindowEvent, msg_bus: &mut MsgBus) {
+/// Main loop state the debug keybinds and cursor conversion need while handling an event
+struct DebugState<'a> {
+    clock: &'a mut GameClock,
+    batch: &'a mut Batch,
+    clip_recorder: Option<&'a ClipRecorder>,
+    render_size: RenderSize,
+}
+
+fn handle_event(
+    window: &mut Window,
+    event: &WindowEvent,
+    debug: DebugState,
+    msg_bus: &mut MsgBus,
+    input: &mut InputState,
+) {
     use glfw::{Action, Key};
 
+    let DebugState {
+        clock,
+        batch,
+        clip_recorder,
+        render_size,
+    } = debug;
+
+    // Debug builds get keybinds to control the simulation clock
+    let debug_keys = cfg!(debug_assertions);
+
     match event {
         // Esc exits the game
         WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
-        // Any other key event gets passed to the game via the message bus
-        WindowEvent::Key(key, _, action, _) => {
-            msg_bus.push(MsgData::Key(*key, *action));
+        // F5 saves the recorded clip, when recording with --record-clips
+        WindowEvent::Key(Key::F5, _, Action::Press, _) => {
+            if let Some(recorder) = clip_recorder {
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+                let path = Path::new(CAPTURE_DIR).join(format!("clip_{}.png", seconds));
+                match recorder.dump(&path) {
+                    Ok(()) => println!("Saved clip to {}", path.display()),
+                    Err(err) => eprintln!("Failed to save clip: {}", err),
+                }
+            }
         }
+        // F6 prints the draw statistics for the last frame
+        WindowEvent::Key(Key::F6, _, Action::Press, _) if debug_keys => {
+            let stats = batch.stats();
+            println!(
+                "Batch: {} draw calls, {} vertices, {} indices",
+                stats.draw_calls, stats.vertices, stats.indices
+            );
+        }
+        // F7 captures the next frame's draw calls to disk
+        WindowEvent::Key(Key::F7, _, Action::Press, _) if debug_keys => batch.capture_frame(),
+        // F8 lists the largest GPU allocations
+        WindowEvent::Key(Key::F8, _, Action::Press, _) if debug_keys => print_gpu_memory(),
+        // F9 pauses/resumes the simulation
+        WindowEvent::Key(Key::F9, _, Action::Press, _) if debug_keys => clock.toggle_paused(),
+        // F10 advances a paused simulation by exactly one tick
+        WindowEvent::Key(Key::F10, _, Action::Press | Action::Repeat, _) if debug_keys => {
+            clock.step_once()
+        }
+        // F11 and F12 halve and double the simulation speed
+        WindowEvent::Key(Key::F11, _, Action::Press, _) if debug_keys => clock.slow_down(),
+        WindowEvent::Key(Key::F12, _, Action::Press, _) if debug_keys => clock.speed_up(),
+        // Any other key event is tracked for held key queries, and passed to the game via the message bus
+        WindowEvent::Key(key, _, action, mods) => {
+            let msg = MsgData::Key(*key, *action, *mods);
+            msg_bus.push(msg);
+            input.handle_msg(&msg, msg_bus);
+        }
+        // Text input gets passed separately from key presses
+        // NOTE: GLFW only reports characters once an IME composition is committed
+        WindowEvent::Char(c) => {
+            msg_bus.push(MsgData::Char(*c));
+        }
+        // Mouse events are passed in virtual screen coordinates, and checked for gestures
+        WindowEvent::CursorPos(x, y) => {
+            let msg = MsgData::MouseMove(cursor_to_virtual(window, render_size, *x, *y));
+            msg_bus.push(msg);
+            input.handle_msg(&msg, msg_bus);
+        }
+        WindowEvent::MouseButton(button, action, mods) => {
+            let (x, y) = window.get_cursor_pos();
+            let msg = MsgData::MouseButton(
+                *button,
+                *action,
+                *mods,
+                cursor_to_virtual(window, render_size, x, y),
+            );
+            msg_bus.push(msg);
+            input.handle_msg(&msg, msg_bus);
+        }
+        // Keys released while the window is unfocused never report a release event
+        WindowEvent::Focus(false) => input.release_all(),
         _ => {}
     }
 }
 
-/// Viewport structure for aspect-ratio correct rendering
-#[derive(Debug, Copy, Clone)]
-struct Viewport {
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
+/// Test every name described by a pattern file against the archive, printing the names found
+/// The pattern file has one pattern (see `mpq::expand_pattern`) per line, '#' starts a comment line.
+fn resolve_names(archive: &Archive, patterns_path: &str) -> anyhow::Result<()> {
+    let patterns = std::fs::read_to_string(patterns_path)
+        .with_context(|| format!("Failed to read name patterns from {}", patterns_path))?;
+    let candidates: Vec<String> = patterns
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(expand_pattern)
+        .collect();
+
+    let mut names = archive.resolve_names(candidates.iter().map(String::as_str));
+    // Archive names are case-insensitive, so only keep one spelling of each
+    names.sort_by_key(|name| name.to_ascii_lowercase());
+    names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    for name in &names {
+        println!("{}", name);
+    }
+    eprintln!(
+        "Resolved {} names from {} candidates, {} files are still unnamed",
+        names.len(),
+        candidates.len(),
+        archive.count_unnamed(&names)
+    );
+    Ok(())
+}
+
+/// Convert a cursor position from window coordinates to virtual screen coordinates
+/// Positions are relative to the UI area, which is what the screens are laid out in
+fn cursor_to_virtual(window: &Window, render_size: RenderSize, x: f64, y: f64) -> (f32, f32) {
+    let window_size = window.get_size();
+    let framebuffer_size = window.get_framebuffer_size();
+    // Window coordinates aren't always framebuffer pixels (e.g. on high DPI displays)
+    let scale_x = framebuffer_size.0 as f64 / window_size.0.max(1) as f64;
+    let scale_y = framebuffer_size.1 as f64 / window_size.1.max(1) as f64;
+    let pos = ((x * scale_x) as f32, (y * scale_y) as f32);
+
+    let projection = Projection::pixel_perfect(render_size.into(), framebuffer_size);
+    let (x, y) = projection.to_virtual(pos, framebuffer_size.1);
+    let ui_offset = render_size.ui_offset();
+    (x - ui_offset.x, y - ui_offset.y)
 }
 
-impl Viewport {
-    /// Calculate the viewport from a given window dimension and a desired aspec ratio
-    pub fn from_window(aspect_ratio: f32, window_size: (i32, i32)) -> Self {
-        let (width, height) = window_size;
-        let mut w = width;
-        let mut h = (w as f32 / aspect_ratio + 0.5f32) as i32;
-        if h > height {
-            h = height;
-            w = (height as f32 * aspect_ratio + 0.5f32) as i32;
+/// Check that every file listed in the screen manifests exists in the archive
+fn verify_assets(archive: &Archive) -> anyhow::Result<()> {
+    let mut missing = 0;
+    for name in GameScreenName::ALL {
+        for key in name.manifest() {
+            if !archive.has_file_by_key(key) {
+                eprintln!("Missing asset for {:?} screen: {}", name, key);
+                missing += 1;
+            }
         }
-        let x = (width - w) / 2;
-        let y = (height - h) / 2;
-        Self { x, y, w, h }
+    }
+    if missing > 0 {
+        bail!("{} required assets are missing", missing);
+    }
+    println!("All required assets are present");
+    Ok(())
+}
+
+/// Print the total GPU memory usage along with the largest allocations
+fn print_gpu_memory() {
+    println!("GPU memory used: {} bytes", gpu_memory_used());
+    for allocation in top_gpu_allocations(10) {
+        println!("    {}", allocation);
     }
 }
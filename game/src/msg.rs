@@ -6,7 +6,25 @@ const TIME_EPS: f64 = 1e-10;
 /// Game message structure
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
 pub enum MsgData {
-    Key(glfw::Key, glfw::Action),
+    Key(glfw::Key, glfw::Action, glfw::Modifiers),
+    /// Text input, after keyboard layout and IME composition have been applied
+    Char(char),
+    /// Cursor movement, in virtual screen coordinates
+    MouseMove((f32, f32)),
+    /// Mouse button press/release, with the cursor position in virtual screen coordinates
+    MouseButton(glfw::MouseButton, glfw::Action, glfw::Modifiers, (f32, f32)),
+    /// A mouse button was pressed and released without dragging
+    Click(glfw::MouseButton, glfw::Modifiers, (f32, f32)),
+    /// Second click of a button in quick succession, sent right after its `Click`
+    DoubleClick(glfw::MouseButton, glfw::Modifiers, (f32, f32)),
+    /// The cursor moved past the drag threshold with a button held, positioned where the button was pressed
+    DragStart(glfw::MouseButton, glfw::Modifiers, (f32, f32)),
+    /// The cursor moved during a drag
+    Drag(glfw::MouseButton, (f32, f32)),
+    /// The dragging button was released
+    DragEnd(glfw::MouseButton, (f32, f32)),
+    /// An animation reached a frame tagged with an event
+    AnimEvent(crate::anim::AnimEvent),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -30,23 +48,24 @@ impl PartialOrd for Msg {
 
 impl Eq for Msg {}
 
+/// Messages are ordered so the earliest (then first pushed) message is the greatest,
+/// since the bus's max-heap pops the greatest message first
 impl Ord for Msg {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         if (self.time - other.time).abs() >= TIME_EPS {
-            if self.time > other.time {
+            if self.time < other.time {
                 Ordering::Greater
             } else {
                 Ordering::Less
             }
         } else {
-            self.id.cmp(&other.id)
+            other.id.cmp(&self.id)
         }
     }
 }
 
 /// In game message bus
 /// Used for asynchronous communication
-/// TODO: Switch to a priority queue to allow events to be delayed and re-ordered
 #[derive(Debug)]
 pub struct MsgBus {
     time: f64,
@@ -102,3 +121,24 @@ impl MsgBus {
         self.queue.is_empty() || self.time < self.queue.peek().unwrap().time
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_order() {
+        let mut msg_bus = MsgBus::new(0);
+        msg_bus.push_delayed(MsgData::Char('c'), 0.5);
+        msg_bus.push(MsgData::Char('a'));
+        msg_bus.push(MsgData::Char('b'));
+        // Messages pop in the order they were pushed, and delayed ones wait for their time
+        assert_eq!(MsgData::Char('a'), msg_bus.pop().unwrap().data);
+        assert_eq!(MsgData::Char('b'), msg_bus.pop().unwrap().data);
+        assert!(msg_bus.is_empty());
+        assert!(msg_bus.pop().is_none());
+        msg_bus.update(0.5);
+        assert_eq!(MsgData::Char('c'), msg_bus.pop().unwrap().data);
+        assert!(msg_bus.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+/// A timed phase of the game's startup
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub name: String,
+    // Number of phases this one was timed inside of
+    pub depth: usize,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Profiler {
+    phases: Vec<Phase>,
+    depth: usize,
+}
+
+thread_local! {
+    // Recorded phases, only present while profiling is enabled
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Start recording timed phases on this thread
+pub fn enable() {
+    PROFILER.with(|profiler| *profiler.borrow_mut() = Some(Profiler::default()));
+}
+
+/// Check if timed phases are being recorded on this thread
+pub fn is_enabled() -> bool {
+    PROFILER.with(|profiler| profiler.borrow().is_some())
+}
+
+/// Run a phase, recording how long it took if profiling is enabled
+/// Phases run inside other phases are nested under them in the report
+pub fn time<T, F>(name: &str, phase: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    // Reserve the phase's slot up front, so phases are reported in the order they started
+    let index = PROFILER.with(|profiler| {
+        profiler.borrow_mut().as_mut().map(|profiler| {
+            profiler.phases.push(Phase {
+                name: name.to_string(),
+                depth: profiler.depth,
+                duration: Duration::ZERO,
+            });
+            profiler.depth += 1;
+            profiler.phases.len() - 1
+        })
+    });
+    let start = Instant::now();
+    let result = phase();
+    let duration = start.elapsed();
+    if let Some(index) = index {
+        PROFILER.with(|profiler| {
+            if let Some(profiler) = profiler.borrow_mut().as_mut() {
+                profiler.phases[index].duration = duration;
+                profiler.depth -= 1;
+            }
+        });
+    }
+    result
+}
+
+/// Stop profiling, returning every recorded phase in the order they started
+pub fn finish() -> Vec<Phase> {
+    PROFILER.with(|profiler| {
+        profiler
+            .borrow_mut()
+            .take()
+            .map(|profiler| profiler.phases)
+            .unwrap_or_default()
+    })
+}
+
+/// Print a breakdown of timed phases
+pub fn print_phases(phases: &[Phase]) {
+    let total: Duration = phases
+        .iter()
+        .filter(|phase| phase.depth == 0)
+        .map(|phase| phase.duration)
+        .sum();
+    println!("Startup took {:.2} ms", total.as_secs_f64() * 1000.0);
+    for phase in phases {
+        println!(
+            "{:indent$}{:>9.2} ms  {}",
+            "",
+            phase.duration.as_secs_f64() * 1000.0,
+            phase.name,
+            indent = 4 + phase.depth * 2
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_phases() {
+        // Nothing is recorded until profiling is enabled
+        assert_eq!(1, time("ignored", || 1));
+        enable();
+        time("outer", || {
+            time("first", || {});
+            time("second", || {});
+        });
+        time("last", || {});
+        let phases = finish();
+        let names: Vec<(&str, usize)> = phases
+            .iter()
+            .map(|phase| (phase.name.as_str(), phase.depth))
+            .collect();
+        assert_eq!(
+            vec![("outer", 0), ("first", 1), ("second", 1), ("last", 0)],
+            names
+        );
+        assert!(phases[0].duration >= phases[1].duration + phases[2].duration);
+        assert!(!is_enabled());
+    }
+}
@@ -1,26 +1,50 @@
+mod stress;
 mod title;
 mod town;
 
+use stress::*;
 use title::*;
 use town::*;
 
-use mpq::Archive;
+use mpq::{Archive, AssetKey};
 
 use gfx::Batch;
 
+use crate::input::InputState;
 use crate::msg::MsgBus;
 
 #[derive(Debug, Clone, Copy)]
 pub enum GameScreenName {
     Title,
     Town,
+    /// Debug screen for profiling the renderer
+    Stress,
 }
 
 impl GameScreenName {
+    /// Every screen in the game
+    pub const ALL: [GameScreenName; 3] = [
+        GameScreenName::Title,
+        GameScreenName::Town,
+        GameScreenName::Stress,
+    ];
+
+    /// Get the list of archive files a screen loads
+    /// Used to prefetch assets before a transition, and to verify the archive is complete
+    pub fn manifest(&self) -> Vec<AssetKey> {
+        match self {
+            GameScreenName::Title => title_manifest(),
+            // The town screen doesn't load anything yet
+            GameScreenName::Town => Vec::new(),
+            GameScreenName::Stress => stress_manifest(),
+        }
+    }
+
     pub fn init(&self, archive: &Archive) -> anyhow::Result<Box<dyn GameScreen>> {
         match self {
             GameScreenName::Title => Ok(Box::new(TitleScreen::new(archive)?)),
             GameScreenName::Town => Ok(Box::new(TownScreen::new(archive)?)),
+            GameScreenName::Stress => Ok(Box::new(StressScreen::new(archive)?)),
         }
     }
 }
@@ -33,7 +57,18 @@ pub trait GameScreen {
     where
         Self: Sized;
     /// Update the game
-    fn update(&mut self, msg_bus: &mut MsgBus, delta: f64) -> Option<GameScreenName>;
+    /// Held key state for the current tick can be queried from the input state
+    fn update(
+        &mut self,
+        msg_bus: &mut MsgBus,
+        input: &InputState,
+        delta: f64,
+    ) -> Option<GameScreenName>;
     // Render the game
     fn render(&self, batch: &mut Batch);
+    /// Check if the screen changes over time without any input
+    /// Frames of static screens are only redrawn when there's input or the window changes
+    fn is_animating(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,195 @@
+use cgmath::*;
+
+use glfw::Key;
+
+use mpq::*;
+
+use gfx::*;
+
+use crate::anim::*;
+use crate::input::*;
+use crate::msg::*;
+use crate::screen::*;
+use crate::*;
+
+/// Every archive file loaded by the stress test screen
+/// NOTE: The sprites are generated, so the screen works without any archive data
+pub fn stress_manifest() -> Vec<AssetKey> {
+    Vec::new()
+}
+
+/// Number of sprites spawned when the screen opens
+const INITIAL_SPRITES: usize = 4000;
+/// Number of sprites added or removed per key press
+const SPRITE_STEP: usize = 1000;
+/// Most sprites the batch can hold in a frame, at 6 vertices per sprite
+const MAX_SPRITES: usize = MAX_BATCH_VERTICES / 6;
+
+/// Number of distinct generated textures, and frames in each of them
+const TEXTURE_COUNT: usize = 4;
+const FRAME_COUNT: usize = 8;
+
+/// Sprite speed range, in pixels per second
+const MIN_SPEED: f32 = 20.0;
+const MAX_SPEED: f32 = 120.0;
+
+/// Debug screen that draws thousands of animated sprites, to profile the batch
+/// Up/Down add and remove sprites, and S toggles drawing the sprites grouped by texture
+/// (few draw calls) or interleaved (a draw call per sprite)
+#[derive(Debug)]
+pub struct StressScreen {
+    textures: Vec<TextureArray>,
+    sprites: Vec<StressSprite>,
+    grouped: bool,
+    // Random number generator state, so every run spawns the same sprites
+    seed: u32,
+}
+
+#[derive(Debug)]
+struct StressSprite {
+    pos: Vector2<f32>,
+    velocity: Vector2<f32>,
+    texture: usize,
+    animation: LoopingTween<Frame>,
+}
+
+impl StressScreen {
+    /// Add a number of sprites, up to the batch limit
+    fn spawn(&mut self, count: usize) {
+        let count = count.min(MAX_SPRITES - self.sprites.len());
+        for _ in 0..count {
+            let pos = vec2(
+                self.random() * RENDER_WIDTH as f32,
+                self.random() * RENDER_HEIGHT as f32,
+            );
+            let angle = self.random() * std::f32::consts::TAU;
+            let speed = MIN_SPEED + self.random() * (MAX_SPEED - MIN_SPEED);
+            let velocity = vec2(angle.cos(), angle.sin()) * speed;
+            let texture = self.sprites.len() % TEXTURE_COUNT;
+            let duration = 0.5 + self.random() as f64;
+            self.sprites.push(StressSprite {
+                pos,
+                velocity,
+                texture,
+                animation: LoopingTween::new(Frame(0), Frame(FRAME_COUNT - 1), duration),
+            });
+        }
+    }
+
+    /// Get the next random number, in the range [0.0, 1.0)
+    fn random(&mut self) -> f32 {
+        // xorshift32
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn draw_sprite(&self, batch: &mut Batch, sprite: &StressSprite) {
+        let frame: usize = sprite.animation.value().into();
+        batch.sprite(
+            &self.textures[sprite.texture],
+            frame as u32,
+            Anchor::Center,
+            Vector2::zero(),
+            Xform2D::position(sprite.pos),
+            Vector4::new(1.0, 1.0, 1.0, 1.0),
+        );
+    }
+}
+
+impl GameScreen for StressScreen {
+    fn new(_archive: &Archive) -> anyhow::Result<Self> {
+        let textures = (0..TEXTURE_COUNT)
+            .map(generate_texture)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut screen = Self {
+            textures,
+            sprites: Vec::new(),
+            grouped: false,
+            seed: 0x2C1B3C6D,
+        };
+        screen.spawn(INITIAL_SPRITES);
+        Ok(screen)
+    }
+
+    fn update(
+        &mut self,
+        msg_bus: &mut MsgBus,
+        input: &InputState,
+        delta: f64,
+    ) -> Option<GameScreenName> {
+        // Only held key state is used, so drop the queued messages
+        while msg_bus.pop().is_some() {}
+
+        if input.is_triggered(Key::Up) {
+            self.spawn(SPRITE_STEP);
+        }
+        if input.is_triggered(Key::Down) {
+            let len = self.sprites.len().saturating_sub(SPRITE_STEP);
+            self.sprites.truncate(len);
+        }
+        if input.was_pressed(Key::S) {
+            self.grouped = !self.grouped;
+        }
+
+        let bounds = vec2(RENDER_WIDTH as f32, RENDER_HEIGHT as f32);
+        for sprite in self.sprites.iter_mut() {
+            sprite.animation.update(delta);
+            sprite.pos += sprite.velocity * delta as f32;
+            // Bounce off the edges of the screen
+            if sprite.pos.x < 0.0 || sprite.pos.x > bounds.x {
+                sprite.velocity.x = -sprite.velocity.x;
+                sprite.pos.x = sprite.pos.x.clamp(0.0, bounds.x);
+            }
+            if sprite.pos.y < 0.0 || sprite.pos.y > bounds.y {
+                sprite.velocity.y = -sprite.velocity.y;
+                sprite.pos.y = sprite.pos.y.clamp(0.0, bounds.y);
+            }
+        }
+
+        None
+    }
+
+    fn render(&self, batch: &mut Batch) {
+        // The sprites stand in for monsters and objects, so they're drawn as part of the world
+        batch.set_pass(RenderPass::World);
+        if self.grouped {
+            for texture in 0..TEXTURE_COUNT {
+                for sprite in self.sprites.iter().filter(|s| s.texture == texture) {
+                    self.draw_sprite(batch, sprite);
+                }
+            }
+        } else {
+            // Neighbouring sprites never share a texture, which is the batch's worst case
+            for sprite in self.sprites.iter() {
+                self.draw_sprite(batch, sprite);
+            }
+        }
+        batch.set_pass(RenderPass::Ui);
+    }
+}
+
+/// Generate a texture of pulsing discs, with a different color and size per texture
+fn generate_texture(index: usize) -> anyhow::Result<TextureArray> {
+    const COLORS: [[u8; 3]; TEXTURE_COUNT] =
+        [[255, 96, 64], [96, 255, 64], [64, 128, 255], [255, 224, 64]];
+
+    let mut builder = TextureArrayBuilder::new(Format::R8g8b8a8_uint, Filtering::Nearest);
+    let size = 8 + 4 * index;
+    for frame in 0..FRAME_COUNT {
+        let radius = size as f32 * 0.5 * (0.5 + 0.5 * frame as f32 / (FRAME_COUNT - 1) as f32);
+        let center = size as f32 * 0.5;
+        let mut pixels = Vec::with_capacity(size * size * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let d = vec2(x as f32 + 0.5 - center, y as f32 + 0.5 - center).magnitude();
+                let alpha = if d <= radius { 255 } else { 0 };
+                pixels.extend_from_slice(&COLORS[index]);
+                pixels.push(alpha);
+            }
+        }
+        builder.push(size, size, &pixels)?;
+    }
+    builder.build()
+}
@@ -6,12 +6,30 @@ use gfx::*;
 
 use crate::anim::*;
 use crate::file::*;
+use crate::input::*;
 use crate::msg::*;
+use crate::profile;
 use crate::screen::*;
 use crate::*;
 
 const COPYRIGHT_TEXT: &'static str = "Copyright © 1996-2001 Blizzard Entertainment";
 
+const TITLE_IMAGE: &str = "ui_art\\title.pcx";
+const LOGO_IMAGE: &str = "ui_art\\logo.pcx";
+const COPYRIGHT_FONT_SIZE: FontSize = FontSize::Size24;
+const COPYRIGHT_FONT_COLOR: FontColor = FontColor::Silver;
+
+/// Every archive file loaded by the title screen
+pub fn title_manifest() -> Vec<AssetKey> {
+    let mut manifest = vec![AssetKey::new(TITLE_IMAGE), AssetKey::new(LOGO_IMAGE)];
+    if let Some((filename_bin, filename_pcx)) =
+        get_font_filenames(COPYRIGHT_FONT_SIZE, COPYRIGHT_FONT_COLOR)
+    {
+        manifest.extend([filename_bin, filename_pcx]);
+    }
+    manifest
+}
+
 /// Game title screen
 /// First screen after the intro video and before the main menu
 #[derive(Debug)]
@@ -20,6 +38,7 @@ pub struct TitleScreen {
     logo_frames: TextureArray,
 
     font: Font,
+    copyright: TextLayout,
 
     logo_animation: LoopingTween<Frame>,
     fade_animation: OneShotTween<Frame>,
@@ -28,39 +47,55 @@ pub struct TitleScreen {
 impl GameScreen for TitleScreen {
     fn new(archive: &Archive) -> anyhow::Result<Self> {
         let title = {
-            let file = archive.get_file("ui_art\\title.pcx")?;
-            Image::read_pcx(&file, None)?.into_texture()?
+            let file = archive.get_file(TITLE_IMAGE)?;
+            let image = profile::time(&format!("Decode {}", TITLE_IMAGE), || {
+                Image::read_pcx(&file, None)
+            })?;
+            profile::time(&format!("Upload {}", TITLE_IMAGE), || image.into_texture())?
         };
 
         let logo_frames = {
             let layers = 15;
             let alpha_index = 250;
 
-            let file = archive.get_file("ui_art\\logo.pcx")?;
-            Image::read_pcx(&file, Some(alpha_index))?.into_texture_array(layers)?
+            let file = archive.get_file(LOGO_IMAGE)?;
+            let image = profile::time(&format!("Decode {}", LOGO_IMAGE), || {
+                Image::read_pcx(&file, Some(alpha_index))
+            })?;
+            profile::time(&format!("Upload {}", LOGO_IMAGE), || {
+                image.into_texture_array(layers)
+            })?
         };
 
-        let font = Font::load(archive, FontSize::Size24, FontColor::Silver)?;
+        let font = Font::load(archive, COPYRIGHT_FONT_SIZE, COPYRIGHT_FONT_COLOR)?;
+        let copyright = font.layout(COPYRIGHT_TEXT);
 
         Ok(Self {
             title,
             logo_frames,
             font,
+            copyright,
             fade_animation: OneShotTween::new(Frame(0), Frame(48), 1.0),
             logo_animation: LoopingTween::new(Frame(0), Frame(14), 1.0),
         })
     }
 
-    fn update(&mut self, msg_bus: &mut MsgBus, delta: f64) -> Option<GameScreenName> {
+    fn update(
+        &mut self,
+        msg_bus: &mut MsgBus,
+        _input: &InputState,
+        delta: f64,
+    ) -> Option<GameScreenName> {
         self.logo_animation.update(delta);
         self.fade_animation.update(delta);
 
         while let Some(msg) = msg_bus.pop() {
             match msg {
                 Msg {
-                    data: MsgData::Key(_key, _action),
+                    data: MsgData::Key(_key, _action, _mods),
                     ..
                 } => return Some(GameScreenName::Town),
+                _ => {}
             }
         }
 
@@ -81,22 +116,16 @@ impl GameScreen for TitleScreen {
         batch.sprite(
             &self.logo_frames,
             frame as u32,
+            Anchor::Center,
+            Vector2::zero(),
             Xform2D::position(pos),
             color_white,
         );
 
-        let text_width = self.font.get_width(COPYRIGHT_TEXT);
-        let text_offset = Vector2::new(((RENDER_WIDTH - text_width) / 2) as f32, 410.0);
-        let char_size = Vector2::new(24.0, 26.0);
-        let text_pos = (char_size * 0.5) + text_offset;
-        for (index, pos) in self.font.render(COPYRIGHT_TEXT, text_pos) {
-            batch.sprite(
-                &self.font.textures,
-                index as u32,
-                Xform2D::position(pos),
-                color_white,
-            );
-        }
+        let text_width = self.copyright.width;
+        let text_pos = Vector2::new(((RENDER_WIDTH - text_width) / 2) as f32, 410.0);
+        self.font
+            .draw(batch, &self.copyright, text_pos, color_white);
 
         if !self.fade_animation.is_done() {
             let fade_alpha = 1.0 - self.fade_animation.percentage();
@@ -2,6 +2,7 @@ use mpq::*;
 
 use gfx::*;
 
+use crate::input::*;
 use crate::msg::*;
 use crate::screen::*;
 
@@ -12,8 +13,16 @@ impl GameScreen for TownScreen {
     fn new(_archive: &Archive) -> anyhow::Result<Self> {
         Ok(Self {})
     }
-    fn update(&mut self, _msg_bus: &mut MsgBus, _delta: f64) -> Option<GameScreenName> {
+    fn update(
+        &mut self,
+        _msg_bus: &mut MsgBus,
+        _input: &InputState,
+        _delta: f64,
+    ) -> Option<GameScreenName> {
         None
     }
     fn render(&self, _batch: &mut Batch) {}
+    fn is_animating(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,533 @@
+use anyhow::Context;
+
+use cgmath::{Vector2, Vector4};
+
+use gfx::Batch;
+
+use crate::file::{charmap, FontColor, FontMetrics, FontSet, FontSize, TextLayout};
+use crate::msg::MsgData;
+
+/// Untinted text
+pub const TEXT_TINT_WHITE: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 1.0);
+/// Tint used for magic item affixes
+pub const TEXT_TINT_BLUE: Vector4<f32> = Vector4::new(0.5, 0.5, 1.0, 1.0);
+/// Tint used for unmet item requirements
+pub const TEXT_TINT_RED: Vector4<f32> = Vector4::new(1.0, 0.3, 0.3, 1.0);
+
+/// A run of text drawn in a single font and tint
+/// Rich text is built from a list of spans
+#[derive(Debug, Clone)]
+pub struct TextSpan {
+    pub size: FontSize,
+    pub color: FontColor,
+    pub tint: Vector4<f32>,
+    pub text: String,
+}
+
+impl TextSpan {
+    /// Create a new, untinted span of text
+    pub fn new(size: FontSize, color: FontColor, text: &str) -> Self {
+        Self {
+            size,
+            color,
+            tint: TEXT_TINT_WHITE,
+            text: text.to_string(),
+        }
+    }
+
+    /// Set the color the span's glyphs are multiplied by
+    pub fn tinted(mut self, tint: Vector4<f32>) -> Self {
+        self.tint = tint;
+        self
+    }
+}
+
+/// A laid out span, positioned relative to the top-left of the text
+#[derive(Debug, Clone)]
+struct TextRun {
+    size: FontSize,
+    color: FontColor,
+    tint: Vector4<f32>,
+    layout: TextLayout,
+    offset: Vector2<f32>,
+}
+
+/// Pre-computed layout of a list of text spans
+/// Spans on the same line are aligned to the bottom of the tallest font on that line
+#[derive(Debug, Clone)]
+pub struct RichText {
+    runs: Vec<TextRun>,
+    /// Size of the laid out text, in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RichText {
+    /// Lay out a list of spans
+    /// NOTE: Every font used by the spans must already be loaded into the font set
+    pub fn layout(fonts: &FontSet, spans: &[TextSpan]) -> anyhow::Result<Self> {
+        Self::layout_with(spans, |size, color| {
+            fonts.get(size, color).map(|font| &**font)
+        })
+    }
+
+    /// Lay out a list of spans, looking up the metrics of each span's font
+    fn layout_with<'a>(
+        spans: &[TextSpan],
+        metrics: impl Fn(FontSize, FontColor) -> Option<&'a FontMetrics>,
+    ) -> anyhow::Result<Self> {
+        let mut runs: Vec<TextRun> = Vec::with_capacity(spans.len());
+
+        // Index of the first run on the current line
+        let mut line_start = 0;
+        // Top edge of the current line, and the tallest glyph/line heights on it
+        let mut line_top = 0.0f32;
+        let mut line_height = 0.0f32;
+        let mut line_advance = 0.0f32;
+
+        let mut pen_x = 0.0f32;
+        let mut width = 0.0f32;
+        for span in spans {
+            let font =
+                metrics(span.size, span.color).context("Font for text span is not loaded")?;
+
+            for (index, segment) in span.text.split('\n').enumerate() {
+                // Every segment after the first starts a new line
+                if index > 0 {
+                    align_line(&mut runs[line_start..], line_height);
+                    line_top += line_advance.max(font.get_line_height() as f32);
+                    line_start = runs.len();
+                    line_height = 0.0;
+                    line_advance = 0.0;
+                    pen_x = 0.0;
+                }
+                if segment.is_empty() {
+                    continue;
+                }
+                let layout = font.layout(segment);
+                let offset = Vector2::new(pen_x, line_top);
+
+                pen_x += layout.width as f32;
+                width = width.max(pen_x);
+                line_height = line_height.max(layout.height as f32);
+                line_advance = line_advance.max(font.get_line_height() as f32);
+
+                runs.push(TextRun {
+                    size: span.size,
+                    color: span.color,
+                    tint: span.tint,
+                    layout,
+                    offset,
+                });
+            }
+        }
+        align_line(&mut runs[line_start..], line_height);
+
+        Ok(Self {
+            runs,
+            width: width as u32,
+            height: (line_top + line_height) as u32,
+        })
+    }
+
+    /// Draw the text with its top-left corner at a position
+    pub fn draw(&self, fonts: &FontSet, batch: &mut Batch, pos: Vector2<f32>) {
+        for run in &self.runs {
+            if let Some(font) = fonts.get(run.size, run.color) {
+                font.draw(batch, &run.layout, pos + run.offset, run.tint);
+            }
+        }
+    }
+}
+
+/// Align every run on a line to the bottom of the line
+fn align_line(runs: &mut [TextRun], line_height: f32) {
+    for run in runs {
+        run.offset.y += line_height - run.layout.height as f32;
+    }
+}
+
+/// Single line, editable text buffer for text input fields (hero names, chat)
+/// Only stores characters the bitmap fonts can draw, and never grows past its maximum length
+#[derive(Debug, Clone)]
+pub struct EditableText {
+    chars: Vec<char>,
+    // Cursor position, as a character index
+    cursor: usize,
+    // Other end of the selection from the cursor, if any text is selected
+    anchor: Option<usize>,
+    // Maximum number of characters
+    max_length: usize,
+}
+
+impl EditableText {
+    /// Create a new, empty text buffer
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            chars: Vec::with_capacity(max_length),
+            cursor: 0,
+            anchor: None,
+            max_length,
+        }
+    }
+
+    /// Get the current text
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Replace the current text, placing the cursor at the end
+    pub fn set_text(&mut self, text: &str) {
+        self.clear();
+        for c in text.chars() {
+            self.insert(c);
+        }
+    }
+
+    /// Remove all text
+    pub fn clear(&mut self) {
+        self.chars.clear();
+        self.cursor = 0;
+        self.anchor = None;
+    }
+
+    /// Get the number of characters in the buffer
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Check if the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Get the cursor position, as a character index
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Get the selected range of characters, in (start, end) form
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        self.anchor
+            .filter(|anchor| *anchor != self.cursor)
+            .map(|anchor| (anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    /// Get the selected text, if any
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection()
+            .map(|(start, end)| self.chars[start..end].iter().collect())
+    }
+
+    /// Insert a character at the cursor, replacing the selection
+    /// Returns false if the character can't be drawn, or the buffer is full
+    pub fn insert(&mut self, c: char) -> bool {
+        let c = match filter_char(c) {
+            Some(c) => c,
+            None => return false,
+        };
+        self.delete_selection();
+        if self.chars.len() >= self.max_length {
+            return false;
+        }
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+        true
+    }
+
+    /// Delete the selection, or the character before the cursor
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Delete the selection, or the character after the cursor
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Move the cursor to a character index, optionally extending the selection
+    pub fn move_to(&mut self, index: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = index.min(self.chars.len());
+    }
+
+    /// Move the cursor one character to the left
+    pub fn move_left(&mut self, select: bool) {
+        match self.selection() {
+            // Collapse the selection to its start
+            Some((start, _)) if !select => self.move_to(start, false),
+            _ => self.move_to(self.cursor.saturating_sub(1), select),
+        }
+    }
+
+    /// Move the cursor one character to the right
+    pub fn move_right(&mut self, select: bool) {
+        match self.selection() {
+            // Collapse the selection to its end
+            Some((_, end)) if !select => self.move_to(end, false),
+            _ => self.move_to(self.cursor + 1, select),
+        }
+    }
+
+    /// Select all of the text
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.chars.len();
+    }
+
+    /// Apply an input message to the buffer
+    /// Returns true if the message was used
+    pub fn handle_msg(&mut self, msg: &MsgData) -> bool {
+        use glfw::{Action, Key, Modifiers};
+
+        match *msg {
+            MsgData::Char(c) => {
+                self.insert(c);
+                true
+            }
+            MsgData::Key(key, Action::Press | Action::Repeat, mods) => {
+                let select = mods.contains(Modifiers::Shift);
+                match key {
+                    Key::Backspace => self.backspace(),
+                    Key::Delete => self.delete(),
+                    Key::Left => self.move_left(select),
+                    Key::Right => self.move_right(select),
+                    Key::Home => self.move_to(0, select),
+                    Key::End => self.move_to(self.chars.len(), select),
+                    Key::A if mods.contains(Modifiers::Control) => self.select_all(),
+                    _ => return false,
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the selected text, returning true if there was a selection
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        match selection {
+            Some((start, end)) => {
+                self.chars.drain(start..end);
+                self.cursor = start;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Get the character to store for a typed character, if the fonts can draw it at all
+fn filter_char(c: char) -> Option<char> {
+    if c.is_control() {
+        return None;
+    }
+    if charmap::encode_cp1252(c).is_some() {
+        return Some(c);
+    }
+    charmap::transliterate(c).filter(|t| charmap::encode_cp1252(*t).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Font metrics with every lowercase letter sharing one advance
+    fn metrics(advance: u8, line_height: u8, glyph_height: usize) -> FontMetrics {
+        let mut bin = vec![0x0u8; 258];
+        bin[0] = 4;
+        bin[1] = line_height;
+        for c in b'a'..=b'z' {
+            bin[c as usize + 2] = advance;
+        }
+        FontMetrics::new(bin, 16, glyph_height)
+    }
+
+    /// Lay out spans in small (16) and large (24) test fonts
+    fn layout(spans: &[TextSpan]) -> RichText {
+        let small = metrics(6, 12, 10);
+        let large = metrics(10, 20, 16);
+        RichText::layout_with(spans, |size, _| match size {
+            FontSize::Size16 => Some(&small),
+            FontSize::Size24 => Some(&large),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    /// Top-left corner of every glyph, relative to the top-left of the text
+    fn glyph_positions(text: &RichText) -> Vec<(f32, f32)> {
+        text.runs
+            .iter()
+            .flat_map(|run| {
+                run.layout.glyphs.iter().map(move |glyph| {
+                    (run.offset.x + glyph.offset.x, run.offset.y + glyph.offset.y)
+                })
+            })
+            .collect()
+    }
+
+    fn small(text: &str) -> TextSpan {
+        TextSpan::new(FontSize::Size16, FontColor::Silver, text)
+    }
+
+    fn large(text: &str) -> TextSpan {
+        TextSpan::new(FontSize::Size24, FontColor::Silver, text)
+    }
+
+    #[test]
+    fn test_layout_baseline() {
+        let text = layout(&[small("ab"), large("c\nd"), small("e")]);
+        // Small glyphs sit on the bottom of the large glyphs sharing their line
+        assert_eq!(
+            vec![
+                (0.0, 6.0),
+                (6.0, 6.0),
+                (12.0, 0.0),
+                (0.0, 20.0),
+                (10.0, 26.0)
+            ],
+            glyph_positions(&text)
+        );
+        assert_eq!((22, 36), (text.width, text.height));
+    }
+
+    #[test]
+    fn test_layout_newlines() {
+        // Each newline advances by the tallest line height on the line it ends
+        let text = layout(&[large("a\n"), small("b\n\nc")]);
+        assert_eq!(
+            vec![(0.0, 0.0), (0.0, 20.0), (0.0, 44.0)],
+            glyph_positions(&text)
+        );
+        assert_eq!((10, 54), (text.width, text.height));
+        // Whitespace only advances the pen
+        let text = layout(&[small("a b")]);
+        assert_eq!(vec![(0.0, 0.0), (10.0, 0.0)], glyph_positions(&text));
+        assert_eq!((16, 10), (text.width, text.height));
+    }
+
+    #[test]
+    fn test_layout_missing_font() {
+        let font = metrics(6, 12, 10);
+        let spans = [small("a"), large("b")];
+        assert!(RichText::layout_with(&spans, |size, _| {
+            (size == FontSize::Size16).then_some(&font)
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_align_line() {
+        let font = metrics(6, 12, 10);
+        let mut runs = vec![TextRun {
+            size: FontSize::Size16,
+            color: FontColor::Silver,
+            tint: TEXT_TINT_WHITE,
+            layout: font.layout("a"),
+            offset: Vector2::new(3.0, 5.0),
+        }];
+        align_line(&mut runs, 16.0);
+        assert_eq!(Vector2::new(3.0, 11.0), runs[0].offset);
+    }
+
+    fn editable(text: &str, max_length: usize) -> EditableText {
+        let mut editable = EditableText::new(max_length);
+        editable.set_text(text);
+        editable
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut text = editable("hero", 8);
+        assert_eq!(4, text.cursor());
+        // Typing replaces the selection
+        text.move_to(1, false);
+        text.move_to(3, true);
+        assert_eq!(Some("er".to_string()), text.selected_text());
+        assert!(text.insert('a'));
+        assert_eq!("hao", text.text());
+        assert_eq!(2, text.cursor());
+        assert_eq!(None, text.selection());
+        // Characters the fonts can't draw are dropped, others are transliterated
+        assert!(!text.insert('\n'));
+        assert!(!text.insert('\u{2603}'));
+        assert_eq!("hao", text.text());
+    }
+
+    #[test]
+    fn test_max_length() {
+        let mut text = editable("toolongname", 4);
+        assert_eq!("tool", text.text());
+        assert!(!text.insert('x'));
+        assert_eq!(4, text.len());
+        // A full buffer still accepts typing over a selection
+        text.select_all();
+        assert!(text.insert('x'));
+        assert_eq!("x", text.text());
+    }
+
+    #[test]
+    fn test_backspace_and_delete() {
+        let mut text = editable("abcd", 8);
+        text.move_to(2, false);
+        text.backspace();
+        assert_eq!(("acd".to_string(), 1), (text.text(), text.cursor()));
+        text.delete();
+        assert_eq!(("ad".to_string(), 1), (text.text(), text.cursor()));
+        // Nothing to delete past either end
+        text.move_to(0, false);
+        text.backspace();
+        text.move_to(2, false);
+        text.delete();
+        assert_eq!("ad", text.text());
+
+        // With a selection, both only remove the selected text
+        let mut text = editable("abcdef", 8);
+        text.move_to(1, false);
+        text.move_to(3, true);
+        text.backspace();
+        assert_eq!(("adef".to_string(), 1), (text.text(), text.cursor()));
+        text.move_to(3, true);
+        text.delete();
+        assert_eq!(("af".to_string(), 1), (text.text(), text.cursor()));
+    }
+
+    #[test]
+    fn test_select_at_ends() {
+        let mut text = editable("abc", 8);
+        // Selecting past the end keeps the cursor at the end, and selects nothing
+        text.move_right(true);
+        assert_eq!((3, None), (text.cursor(), text.selection()));
+        text.move_left(true);
+        text.move_left(true);
+        assert_eq!(Some((1, 3)), text.selection());
+        // Moving without shift collapses the selection to the side moved towards
+        text.move_right(false);
+        assert_eq!((3, None), (text.cursor(), text.selection()));
+
+        text.move_to(0, false);
+        text.move_left(true);
+        assert_eq!((0, None), (text.cursor(), text.selection()));
+        text.move_right(true);
+        assert_eq!(Some((0, 1)), text.selection());
+        // Selecting back to the anchor leaves nothing selected
+        text.move_to(2, true);
+        text.move_left(true);
+        text.move_left(true);
+        text.move_left(true);
+        assert_eq!((0, None), (text.cursor(), text.selection()));
+        text.move_left(false);
+        assert_eq!((0, None), (text.cursor(), text.selection()));
+    }
+}
@@ -7,17 +7,162 @@ use gl::types::*;
 
 use cgmath::*;
 
-use super::Xform2D;
+use super::{Anchor, Region, Xform2D};
 
+use super::capture::{CapturedRange, CapturedVertex, FrameCapture};
 use super::gpu::*;
 use super::material::{Material, MaterialMap};
 
+/// Largest number of vertices a batch can hold, as vertices are addressed by 16-bit indices
+pub const MAX_BATCH_VERTICES: usize = u16::MAX as usize + 1;
+
+/// Statistics for the geometry recorded in a batch, used for profiling
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /// Number of draw calls the batch renders with
+    pub draw_calls: usize,
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+/// Set of uniforms (projection) a draw request is rendered with
+/// Lets the UI stay pixel-aligned in screen space while the camera pans and zooms the world
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UniformSet {
+    World,
+    Screen,
+}
+
+impl UniformSet {
+    /// Number of uniform sets in a batch
+    pub const COUNT: usize = 2;
+
+    fn index(&self) -> usize {
+        match self {
+            UniformSet::World => 0,
+            UniformSet::Screen => 1,
+        }
+    }
+}
+
+/// Blending mode a render pass is drawn with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Standard alpha blending
+    Alpha,
+    /// Additive blending, for light-like effects such as spell particles
+    Additive,
+}
+
+impl BlendMode {
+    /// Set the GL blending function for this mode
+    fn apply(&self) {
+        unsafe {
+            match self {
+                BlendMode::Alpha => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+                BlendMode::Additive => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE),
+            }
+        }
+    }
+}
+
+/// Render pass a draw request is recorded in
+/// Passes render in a fixed order (world, particles, screen effects, UI, then debug overlays),
+/// no matter what order their draw requests were recorded in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RenderPass {
+    World,
+    Particles,
+    /// Effects covering the whole render area, such as damage flashes
+    Overlay,
+    Ui,
+    Debug,
+}
+
+impl RenderPass {
+    /// Every render pass, in rendering order
+    pub const ALL: [RenderPass; 5] = [
+        RenderPass::World,
+        RenderPass::Particles,
+        RenderPass::Overlay,
+        RenderPass::Ui,
+        RenderPass::Debug,
+    ];
+
+    /// Get the uniform set draws in this pass use by default
+    pub fn uniform_set(&self) -> UniformSet {
+        match self {
+            RenderPass::World | RenderPass::Particles => UniformSet::World,
+            RenderPass::Overlay | RenderPass::Ui | RenderPass::Debug => UniformSet::Screen,
+        }
+    }
+
+    /// Get the blending mode this pass renders with
+    pub fn blend_mode(&self) -> BlendMode {
+        match self {
+            RenderPass::Particles => BlendMode::Additive,
+            _ => BlendMode::Alpha,
+        }
+    }
+}
+
+/// Per-draw color adjustment, applied after texturing and tinting
+/// Used for disabled UI buttons, petrified monsters and pause dimming
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorEffect {
+    /// Color saturation, from 0.0 (grayscale) to 1.0 (unchanged)
+    pub saturation: f32,
+    /// Brightness multiplier, 1.0 is unchanged
+    pub brightness: f32,
+}
+
+impl ColorEffect {
+    /// No color adjustment
+    pub const NONE: Self = Self {
+        saturation: 1.0,
+        brightness: 1.0,
+    };
+
+    /// Fully desaturated, at a given brightness
+    pub fn grayscale(brightness: f32) -> Self {
+        Self {
+            saturation: 0.0,
+            brightness,
+        }
+    }
+
+    /// Full color, at a given brightness
+    pub fn dimmed(brightness: f32) -> Self {
+        Self {
+            saturation: 1.0,
+            brightness,
+        }
+    }
+}
+
+impl Default for ColorEffect {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl From<ColorEffect> for Vector2<f32> {
+    fn from(effect: ColorEffect) -> Self {
+        vec2(effect.saturation, effect.brightness)
+    }
+}
+
 /// Geometry batching renderer
 /// Records draw requests and transforms them into GPU-usable data
 #[derive(Debug)]
 pub struct Batch {
     // The current ranges to draw
     ranges: Vec<Range>,
+    // Projection matrix for each uniform set, and the set used by new draw requests
+    projections: [Matrix4<f32>; UniformSet::COUNT],
+    uniform_set: UniformSet,
+    // Render pass used by new draw requests
+    pass: RenderPass,
 
     // VBO/VAO objects
     // TODO: Utilize ring buffers to enqueue frames for rendering
@@ -25,13 +170,25 @@ pub struct Batch {
     indices: DynamicBuffer<u16>,
     vertices: DynamicBuffer<Vertex>,
     vertex_array: VertexArray,
+
+    // Color adjustment applied to new draw requests
+    effect: ColorEffect,
+    // Materials whose vertex positions are snapped to the virtual pixel grid
+    snapped_materials: Vec<Material>,
+
+    // Debug frame capture state
+    capture_requested: bool,
+    capture: Option<FrameCapture>,
 }
 
 impl Batch {
     /// Create a new batch with a specified maximum number of vertices and indices
+    /// NOTE: Panics if the batch can hold more vertices than its indices can address
     pub fn new(max_vertices: usize, max_indices: usize) -> Self {
+        assert!(max_vertices <= MAX_BATCH_VERTICES);
         // Allocate buffers
-        let uniforms: DynamicBuffer<Uniforms> = DynamicBuffer::new(gl::UNIFORM_BUFFER, 1, None);
+        let uniforms: DynamicBuffer<Uniforms> =
+            DynamicBuffer::new(gl::UNIFORM_BUFFER, UniformSet::COUNT, None);
         let indices: DynamicBuffer<u16> =
             DynamicBuffer::new(gl::ELEMENT_ARRAY_BUFFER, max_indices, None);
         let vertices: DynamicBuffer<Vertex> =
@@ -52,10 +209,17 @@ impl Batch {
 
         Self {
             ranges: Vec::new(),
+            projections: [Matrix4::identity(); UniformSet::COUNT],
+            uniform_set: UniformSet::Screen,
+            pass: RenderPass::Ui,
             uniforms,
             indices,
             vertices,
             vertex_array,
+            effect: ColorEffect::NONE,
+            snapped_materials: Vec::new(),
+            capture_requested: false,
+            capture: None,
         }
     }
 
@@ -65,14 +229,97 @@ impl Batch {
         self.indices.clear();
         self.ranges.clear();
     }
-    // Flush any recorded draw data (including the projection matrix)
-    pub fn flush(&mut self, projection: Matrix4<f32>) {
+    /// Get statistics for the geometry recorded since the last clear
+    pub fn stats(&self) -> BatchStats {
+        BatchStats {
+            draw_calls: self.ranges.len(),
+            vertices: self.vertices.len(),
+            indices: self.indices.len(),
+        }
+    }
+
+    /// Set the projection matrix of a uniform set
+    /// NOTE: Projections are kept between frames, and uploaded by `flush`
+    pub fn set_projection(&mut self, set: UniformSet, projection: Matrix4<f32>) {
+        self.projections[set.index()] = projection;
+    }
+
+    /// Set the render pass used by every following draw request
+    /// This also switches to the pass' default uniform set, which can be overridden afterwards
+    /// NOTE: The pass is kept between frames, and defaults to `RenderPass::Ui`
+    pub fn set_pass(&mut self, pass: RenderPass) {
+        self.pass = pass;
+        self.uniform_set = pass.uniform_set();
+    }
+
+    /// Get the render pass used by new draw requests
+    pub fn pass(&self) -> RenderPass {
+        self.pass
+    }
+
+    /// Set the uniform set used by every following draw request
+    /// NOTE: The set is kept between frames, and defaults to `UniformSet::Screen`
+    pub fn set_uniform_set(&mut self, set: UniformSet) {
+        self.uniform_set = set;
+    }
+
+    /// Get the uniform set used by new draw requests
+    pub fn uniform_set(&self) -> UniformSet {
+        self.uniform_set
+    }
+
+    // Flush any recorded draw data (including the projection matrices)
+    pub fn flush(&mut self) {
         self.uniforms.clear();
-        self.uniforms.push(Uniforms { projection });
+        for projection in self.projections {
+            self.uniforms.push(Uniforms { projection });
+        }
         self.uniforms.flush();
 
         self.vertices.flush();
         self.indices.flush();
+
+        if self.capture_requested {
+            self.capture_requested = false;
+            self.capture = Some(self.record_capture());
+        }
+    }
+
+    /// Set the color adjustment applied to every following draw request
+    /// NOTE: The effect is kept between frames, reset it with `ColorEffect::NONE` when done
+    pub fn set_effect(&mut self, effect: ColorEffect) {
+        self.effect = effect;
+    }
+
+    /// Get the current color adjustment
+    pub fn effect(&self) -> ColorEffect {
+        self.effect
+    }
+
+    /// Enable or disable snapping vertex positions to whole pixels for a material
+    /// Snapping stops sprites from shimmering when the window scale isn't an integer multiple
+    /// NOTE: Positions are snapped after transformation, so rotated quads may be slightly distorted
+    pub fn set_pixel_snap(&mut self, material: Material, enabled: bool) {
+        self.snapped_materials.retain(|m| *m != material);
+        if enabled {
+            self.snapped_materials.push(material);
+        }
+    }
+
+    /// Check if a material's vertex positions are snapped to whole pixels
+    pub fn is_pixel_snapped(&self, material: Material) -> bool {
+        self.snapped_materials.contains(&material)
+    }
+
+    /// Request a capture of every draw call in the next flushed frame
+    /// NOTE: Retrieve the capture with `take_capture` once the frame has been flushed
+    pub fn capture_frame(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// Take the most recently completed frame capture, if any
+    pub fn take_capture(&mut self) -> Option<FrameCapture> {
+        self.capture.take()
     }
 
     /// Draw an Axis Aligned Bounding Box (i.e. a non-rotatable, unfilled rectangle)
@@ -80,6 +327,8 @@ impl Batch {
     pub fn aabb(&mut self, pos: Vector2<f32>, size: Vector2<f32>, color: Vector4<f32>) {
         const INDEX_PATTERN: [usize; 6] = [0, 1, 2, 0, 3, 2];
 
+        let fx: Vector2<f32> = self.effect.into();
+
         let hw = size.x * 0.5;
         let hh = size.y * 0.5;
 
@@ -101,6 +350,7 @@ impl Batch {
                         pos: *pos,
                         uv: Vector3::zero(),
                         col: color,
+                        fx,
                     };
                     quad_indices[i] = vertices.push(vertex) as u16;
                 }
@@ -112,15 +362,33 @@ impl Batch {
     }
 
     /// Draw a textured, colored quad using the specified transform
-    /// TODO: Support specifying the portion of the texture to draw
     pub fn image(&mut self, texture: &Texture, xform: Xform2D, color: Vector4<f32>) {
+        let region = Region::new(0.0, 0.0, texture.width as f32, texture.height as f32);
+        self.image_region(texture, region, xform, color);
+    }
+
+    /// Draw a portion of a texture as a textured, colored quad using the specified transform
+    /// The region is measured in pixels from the top-left of the texture
+    pub fn image_region(
+        &mut self,
+        texture: &Texture,
+        region: Region,
+        xform: Xform2D,
+        color: Vector4<f32>,
+    ) {
         const INDEX_PATTERN: [usize; 6] = [0, 1, 2, 0, 3, 2];
 
+        let fx: Vector2<f32> = self.effect.into();
+
         let size = (texture.width as f32, texture.height as f32);
-        let _i_size = vec2(1.0 / size.0, 1.0 / size.1);
+        let i_size = vec2(1.0 / size.0, 1.0 / size.1);
 
-        let (s0, t0, s1, t1) = (0.0, 0.0, 1.0, 1.0);
-        let (_x, _y, w, h) = (0.0, 0.0, size.0, size.1);
+        // NOTE: Textures are stored bottom row first, so the region is flipped vertically
+        let s0 = region.x * i_size.x;
+        let s1 = (region.x + region.w) * i_size.x;
+        let t0 = 1.0 - (region.y + region.h) * i_size.y;
+        let t1 = 1.0 - region.y * i_size.y;
+        let (w, h) = (region.w, region.h);
 
         let hw = w * 0.5;
         let hh = h * 0.5;
@@ -130,21 +398,25 @@ impl Batch {
                 pos: xform * vec2(-hw, -hh),
                 uv: vec3(s0, t1, 0.0),
                 col: color,
+                fx,
             },
             Vertex {
                 pos: xform * vec2(hw, -hh),
                 uv: vec3(s1, t1, 0.0),
                 col: color,
+                fx,
             },
             Vertex {
                 pos: xform * vec2(hw, hh),
                 uv: vec3(s1, t0, 0.0),
                 col: color,
+                fx,
             },
             Vertex {
                 pos: xform * vec2(-hw, hh),
                 uv: vec3(s0, t0, 0.0),
                 col: color,
+                fx,
             },
         ];
 
@@ -164,53 +436,114 @@ impl Batch {
         );
     }
 
-    /// Draw a textured, colored quad using the specified transform
-    /// TODO: Support specifying the portion of the texture to draw
+    /// Draw a layer of a texture array as a textured, colored quad using the specified transform
+    /// The anchor picks the point of the layer placed at the transform's position,
+    /// and the offset (in pixels) nudges the sprite away from it.
+    /// NOTE: Arrays built from differently sized frames only draw the area covered by the layer's frame
     pub fn sprite(
         &mut self,
         texture: &TextureArray,
         layer: u32,
+        anchor: Anchor,
+        offset: Vector2<f32>,
+        xform: Xform2D,
+        color: Vector4<f32>,
+    ) {
+        let origin = anchor.origin(texture.width as f32, texture.height as f32) - offset;
+        self.layer_quad(
+            Material::LayeredTexture,
+            texture,
+            layer,
+            origin,
+            xform,
+            color,
+        );
+    }
+
+    /// Draw a 1 pixel outline around the opaque pixels of a sprite, in a solid color
+    /// Used to highlight hovered monsters and NPCs, takes the same placement as `sprite`
+    /// NOTE: Only the outline itself is drawn, so it can be drawn before or after the sprite
+    pub fn sprite_outline(
+        &mut self,
+        texture: &TextureArray,
+        layer: u32,
+        anchor: Anchor,
+        offset: Vector2<f32>,
+        xform: Xform2D,
+        color: Vector4<f32>,
+    ) {
+        let origin = anchor.origin(texture.width as f32, texture.height as f32) - offset;
+        self.layer_quad(Material::Outline, texture, layer, origin, xform, color);
+    }
+
+    /// Push a quad covering a texture array layer's frame
+    /// The origin is the point of the layer (in pixels from its top-left) placed at the transform's position
+    fn layer_quad(
+        &mut self,
+        material: Material,
+        texture: &TextureArray,
+        layer: u32,
+        origin: Vector2<f32>,
         xform: Xform2D,
         color: Vector4<f32>,
     ) {
         const INDEX_PATTERN: [usize; 6] = [0, 1, 2, 0, 3, 2];
 
+        let fx: Vector2<f32> = self.effect.into();
+
         let size = (texture.width as f32, texture.height as f32);
-        let _i_size = vec2(1.0 / size.0, 1.0 / size.1);
+        let i_size = vec2(1.0 / size.0, 1.0 / size.1);
+
+        // Outlines extend one pixel past the frame on every side
+        let border = match material {
+            Material::Outline => 1.0,
+            _ => 0.0,
+        };
+        let frame = texture.frame(layer as usize);
+        let (x, y) = (frame.x - border, frame.y - border);
+        let (w, h) = (frame.w + border * 2.0, frame.h + border * 2.0);
 
+        // NOTE: Textures are stored bottom row first, so the frame is flipped vertically
         let l = layer as f32;
-        let (s0, t0, s1, t1) = (0.0, 0.0, 1.0, 1.0);
-        let (_x, _y, w, h) = (0.0, 0.0, size.0, size.1);
+        let s0 = x * i_size.x;
+        let s1 = (x + w) * i_size.x;
+        let t0 = 1.0 - (y + h) * i_size.y;
+        let t1 = 1.0 - y * i_size.y;
 
-        let hw = w * 0.5;
-        let hh = h * 0.5;
+        // Quad corners, relative to the origin
+        let (x0, y0) = (x - origin.x, y - origin.y);
+        let (x1, y1) = (x0 + w, y0 + h);
 
         let verts = [
             Vertex {
-                pos: xform * vec2(-hw, -hh),
+                pos: xform * vec2(x0, y0),
                 uv: vec3(s0, t1, l),
                 col: color,
+                fx,
             },
             Vertex {
-                pos: xform * vec2(hw, -hh),
+                pos: xform * vec2(x1, y0),
                 uv: vec3(s1, t1, l),
                 col: color,
+                fx,
             },
             Vertex {
-                pos: xform * vec2(hw, hh),
+                pos: xform * vec2(x1, y1),
                 uv: vec3(s1, t0, l),
                 col: color,
+                fx,
             },
             Vertex {
-                pos: xform * vec2(-hw, hh),
+                pos: xform * vec2(x0, y1),
                 uv: vec3(s0, t0, l),
                 col: color,
+                fx,
             },
         ];
 
         self.push_range(
             Topology::Triangles,
-            Material::LayeredTexture,
+            material,
             texture.handle,
             |vertices, indices| {
                 let mut sprite_indices = [0u16; 6];
@@ -235,19 +568,67 @@ impl Batch {
         // Bind the draw buffers
         self.indices.bind();
         self.vertices.bind();
-        // Bind the uniform range
-        let offset = 0;
+        // For each range, bind the pipeline and uniform range and issue the draw call
         let binding = 0;
-        self.uniforms.bind_range(binding, offset);
-
-        // For each range, bind the pipeline and issue the draw call
-        for range in &self.ranges {
+        let mut bound_set = None;
+        let mut blend_mode = None;
+        for range in self.ordered_ranges() {
+            let pass_blend_mode = range.pass.blend_mode();
+            if blend_mode != Some(pass_blend_mode) {
+                pass_blend_mode.apply();
+                blend_mode = Some(pass_blend_mode);
+            }
+            if bound_set != Some(range.uniforms) {
+                let offset = (range.uniforms.index() * size_of::<Uniforms>()) as isize;
+                self.uniforms.bind_range(binding, offset);
+                bound_set = Some(range.uniforms);
+            }
             range.render(index_format, materials);
         }
+        // Leave the default blending function bound
+        if blend_mode != Some(BlendMode::Alpha) {
+            BlendMode::Alpha.apply();
+        }
         self.uniforms.unbind();
         self.vertex_array.unbind();
     }
 
+    /// Get the recorded ranges in rendering order
+    fn ordered_ranges(&self) -> impl Iterator<Item = &Range> {
+        RenderPass::ALL
+            .into_iter()
+            .flat_map(move |pass| self.ranges.iter().filter(move |range| range.pass == pass))
+    }
+
+    /// Record the current draw ranges, along with their de-indexed vertex data
+    fn record_capture(&self) -> FrameCapture {
+        let vertices = self.vertices.as_slice();
+        let indices = self.indices.as_slice();
+        let ranges = self
+            .ordered_ranges()
+            .map(|range| CapturedRange {
+                pass: range.pass,
+                topology: range.topology,
+                material: range.material,
+                uniforms: range.uniforms,
+                texture: range.texture,
+                vertices: indices[range.offset..range.offset + range.count]
+                    .iter()
+                    .map(|index| {
+                        let vertex = &vertices[*index as usize];
+                        CapturedVertex {
+                            pos: vertex.pos.into(),
+                            uv: vertex.uv.into(),
+                            col: vertex.col.into(),
+                            fx: vertex.fx.into(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        FrameCapture { ranges }
+    }
+
     #[inline]
     fn push_range<F>(
         &mut self,
@@ -258,8 +639,12 @@ impl Batch {
     ) where
         F: FnMut(&mut DynamicBuffer<Vertex>, &mut DynamicBuffer<u16>),
     {
-        // Get the current offset
+        // Get the current offsets
         let offset = self.indices.len();
+        let vertex_offset = self.vertices.len();
+        let snap = self.is_pixel_snapped(material);
+        let uniforms = self.uniform_set;
+        let pass = self.pass;
         // Get the range to draw to
         let mut range = {
             // If the range list is empty, or the current range doesn't match draw parameters
@@ -268,13 +653,15 @@ impl Batch {
                     .ranges
                     .last()
                     .unwrap()
-                    .should_change(texture, topology, material)
+                    .should_change(pass, texture, topology, material, uniforms)
             {
                 // Push a new range
                 let range = Range {
+                    pass,
                     texture,
                     topology,
                     material,
+                    uniforms,
                     offset,
                     count: 0,
                 };
@@ -285,6 +672,12 @@ impl Batch {
         };
         // Execute the draw function
         draw_fn(&mut self.vertices, &mut self.indices);
+        // Snap the new vertices to the pixel grid
+        if snap {
+            for vertex in &mut self.vertices.as_mut_slice()[vertex_offset..] {
+                vertex.pos = vec2(vertex.pos.x.round(), vertex.pos.y.round());
+            }
+        }
         // Add the new indices to the range count
         range.count += self.indices.len() - offset;
     }
@@ -296,10 +689,12 @@ struct Vertex {
     pos: Vector2<f32>,
     uv: Vector3<f32>,
     col: Vector4<f32>,
+    // Color effect, in (saturation, brightness) form
+    fx: Vector2<f32>,
 }
 
 /// Layout descriptor for the vertex structure
-const VERTEX_LAYOUT: [VertexLayout; 3] = [
+const VERTEX_LAYOUT: [VertexLayout; 4] = [
     VertexLayout {
         format: Format::R32g32_float,
         stride: size_of::<Vertex>(),
@@ -315,6 +710,11 @@ const VERTEX_LAYOUT: [VertexLayout; 3] = [
         stride: size_of::<Vertex>(),
         offset: offset_of!(Vertex, col) as usize,
     },
+    VertexLayout {
+        format: Format::R32g32_float,
+        stride: size_of::<Vertex>(),
+        offset: offset_of!(Vertex, fx) as usize,
+    },
 ];
 
 /// Range object
@@ -322,9 +722,11 @@ const VERTEX_LAYOUT: [VertexLayout; 3] = [
 /// Each draw call is represented by a Range object, describing the material/topolgy/and offset into the index buffer
 #[derive(Debug)]
 struct Range {
+    pass: RenderPass,
     texture: u32,
     topology: Topology,
     material: Material,
+    uniforms: UniformSet,
 
     offset: usize,
     count: usize,
@@ -332,8 +734,19 @@ struct Range {
 
 impl Range {
     /// Does this range match a pending draw call?
-    fn should_change(&self, texture: u32, topology: Topology, material: Material) -> bool {
-        self.texture != texture || self.topology != topology || self.material != material
+    fn should_change(
+        &self,
+        pass: RenderPass,
+        texture: u32,
+        topology: Topology,
+        material: Material,
+        uniforms: UniformSet,
+    ) -> bool {
+        self.pass != pass
+            || self.texture != texture
+            || self.topology != topology
+            || self.material != material
+            || self.uniforms != uniforms
     }
 
     /// Render the range
@@ -352,7 +765,7 @@ impl Range {
                 Material::Textured => {
                     gl::BindTexture(gl::TEXTURE_2D, self.texture);
                 }
-                Material::LayeredTexture => {
+                Material::LayeredTexture | Material::Outline => {
                     gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
                 }
                 _ => {}
@@ -364,7 +777,7 @@ impl Range {
                 Material::Textured => {
                     gl::BindTexture(gl::TEXTURE_2D, 0);
                 }
-                Material::LayeredTexture => {
+                Material::LayeredTexture | Material::Outline => {
                     gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
                 }
                 _ => {}
@@ -375,9 +788,10 @@ impl Range {
 }
 
 /// Uniform structure for batch rendering
-/// NOTE: Only one uniform buffer per batch
+/// NOTE: Aligned to 256 bytes, the largest uniform buffer offset alignment GL allows,
+/// so every set in the buffer can be bound with `bind_range`
 #[derive(Clone, Debug)]
-#[repr(C)]
+#[repr(C, align(256))]
 struct Uniforms {
     projection: Matrix4<f32>,
 }
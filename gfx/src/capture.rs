@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::fs;
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
+use std::path::Path;
+
+use super::batch::{RenderPass, UniformSet};
+use super::material::Material;
+use super::png::{write_apng, write_png};
+use super::util::Viewport;
+use super::Topology;
+
+/// Maximum width or height of a dumped texture thumbnail, in pixels
+const THUMBNAIL_SIZE: usize = 128;
+
+/// A single vertex, as recorded by a frame capture
+#[derive(Debug, Copy, Clone)]
+pub struct CapturedVertex {
+    pub pos: [f32; 2],
+    pub uv: [f32; 3],
+    pub col: [f32; 4],
+    pub fx: [f32; 2],
+}
+
+/// A single draw call, as recorded by a frame capture
+#[derive(Debug, Clone)]
+pub struct CapturedRange {
+    pub pass: RenderPass,
+    pub topology: Topology,
+    pub material: Material,
+    pub uniforms: UniformSet,
+    pub texture: u32,
+    // De-indexed vertex data, in draw order
+    pub vertices: Vec<CapturedVertex>,
+}
+
+/// Recording of every draw call submitted to a batch during one frame
+/// Used to make rendering bugs reproducible without an interactive debugger
+#[derive(Debug, Clone, Default)]
+pub struct FrameCapture {
+    pub ranges: Vec<CapturedRange>,
+}
+
+impl FrameCapture {
+    /// Dump the capture into a directory
+    /// The draw calls are written to `frame.json`, along with a PNG thumbnail of every texture layer used.
+    /// NOTE: Textures are read back from the GPU, so this must be called while they are still alive
+    pub fn dump<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut json = BufWriter::new(fs::File::create(dir.join("frame.json"))?);
+        self.write_json(&mut json)?;
+        json.flush()?;
+
+        for (material, texture, layer) in self.texture_layers() {
+            let layer_pixels = unsafe { read_texture_layer(material, texture, layer) };
+            if let Some((width, height, pixels)) = layer_pixels {
+                let (width, height, pixels) = thumbnail(width, height, &pixels);
+                let filename = format!("texture_{}_{}.png", texture, layer);
+                let mut file = BufWriter::new(fs::File::create(dir.join(filename))?);
+                write_png(&mut file, width, height, &pixels)?;
+                file.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the captured draw calls as JSON
+    pub fn write_json<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"ranges\": [")?;
+        for (i, range) in self.ranges.iter().enumerate() {
+            writeln!(out, "    {{")?;
+            writeln!(out, "      \"pass\": \"{:?}\",", range.pass)?;
+            writeln!(out, "      \"topology\": \"{:?}\",", range.topology)?;
+            writeln!(out, "      \"material\": \"{:?}\",", range.material)?;
+            writeln!(out, "      \"uniforms\": \"{:?}\",", range.uniforms)?;
+            writeln!(out, "      \"texture\": {},", range.texture)?;
+            writeln!(out, "      \"vertices\": [")?;
+            for (j, v) in range.vertices.iter().enumerate() {
+                let separator = if j + 1 < range.vertices.len() {
+                    ","
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "        {{ \"pos\": {:?}, \"uv\": {:?}, \"col\": {:?}, \"fx\": {:?} }}{}",
+                    v.pos, v.uv, v.col, v.fx, separator
+                )?;
+            }
+            writeln!(out, "      ]")?;
+            let separator = if i + 1 < self.ranges.len() { "," } else { "" };
+            writeln!(out, "    }}{}", separator)?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    /// Get every unique (material, texture, layer) triple sampled by this frame
+    fn texture_layers(&self) -> Vec<(Material, u32, u32)> {
+        let mut layers: Vec<(Material, u32, u32)> = Vec::new();
+        for range in &self.ranges {
+            for vertex in &range.vertices {
+                let layer = match range.material {
+                    Material::Textured => 0,
+                    Material::LayeredTexture | Material::Outline => vertex.uv[2] as u32,
+                    _ => continue,
+                };
+                let entry = (range.material, range.texture, layer);
+                if !layers.contains(&entry) {
+                    layers.push(entry);
+                }
+            }
+        }
+        layers
+    }
+}
+
+/// Recording of the last few seconds of rendered frames, dumped as an animated PNG on request
+/// Frames are read back and downscaled as they're recorded, which stalls the GPU once per
+/// recorded frame, so recording should be opt-in
+#[derive(Debug)]
+pub struct ClipRecorder {
+    // Size frames are stored at
+    width: usize,
+    height: usize,
+    // Longest time kept, and shortest time between recorded frames, in seconds
+    duration: f64,
+    frame_interval: f64,
+    // Recorded frames and the times they were rendered at, oldest first
+    frames: VecDeque<(f64, Vec<u8>)>,
+}
+
+impl ClipRecorder {
+    /// Create a recorder keeping `duration` seconds of frames, recorded at up to `fps` frames per second
+    pub fn new(width: usize, height: usize, duration: f64, fps: f64) -> Self {
+        Self {
+            width,
+            height,
+            duration,
+            frame_interval: 1.0 / fps,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Record the viewport of the framebuffer, if enough time has passed since the last frame
+    /// `time` is the current time in seconds, which frame delays are measured with.
+    /// NOTE: This reads the back buffer, so it must be called after rendering and before swapping buffers
+    pub fn record(&mut self, time: f64, viewport: &Viewport) {
+        if let Some((last_time, _)) = self.frames.back() {
+            if time - last_time < self.frame_interval {
+                return;
+            }
+        }
+        let (width, height) = (viewport.w.max(0) as usize, viewport.h.max(0) as usize);
+        if width == 0 || height == 0 {
+            return;
+        }
+        let pixels = unsafe { read_viewport(viewport) };
+        let frame = downscale_flipped(width, height, &pixels, self.width, self.height);
+        self.frames.push_back((time, frame));
+        // Drop the frames that are too old to be part of the clip
+        while let Some((first_time, _)) = self.frames.front() {
+            if time - first_time <= self.duration {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    /// Dump the recorded frames to an animated PNG file
+    /// Each frame is shown until the next one was rendered, so frames repeated on idle screens
+    /// are stored once, with a longer delay
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if self.frames.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "No frames recorded"));
+        }
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let frames: Vec<(&[u8], u16)> = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(index, (time, pixels))| {
+                let next_time = self
+                    .frames
+                    .get(index + 1)
+                    .map_or(time + self.frame_interval, |(next_time, _)| *next_time);
+                let delay = ((next_time - time) * 1000.0)
+                    .round()
+                    .clamp(1.0, u16::MAX as f64);
+                (pixels.as_slice(), delay as u16)
+            })
+            .collect();
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        write_apng(&mut file, self.width, self.height, &frames)?;
+        file.flush()
+    }
+}
+
+/// Read the pixels of a viewport from the bound framebuffer, bottom row first
+unsafe fn read_viewport(viewport: &Viewport) -> Vec<u8> {
+    let mut pixels = vec![0x0u8; viewport.w as usize * viewport.h as usize * 4];
+    gl::ReadPixels(
+        viewport.x,
+        viewport.y,
+        viewport.w,
+        viewport.h,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as *mut c_void,
+    );
+    pixels
+}
+
+/// Downscale (nearest neighbour) a bottom-up RGBA image to a given top-down size
+/// Alpha is made opaque, as the framebuffer's alpha isn't meaningful
+fn downscale_flipped(
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+    out_width: usize,
+    out_height: usize,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(out_width * out_height * 4);
+    for y in 0..out_height {
+        let src_y = height - 1 - usize::min(y * height / out_height, height - 1);
+        for x in 0..out_width {
+            let src_x = usize::min(x * width / out_width, width - 1);
+            let index = (src_y * width + src_x) * 4;
+            out.extend_from_slice(&pixels[index..index + 3]);
+            out.push(0xFF);
+        }
+    }
+    out
+}
+
+/// Read a single layer of a texture back from the GPU as top-down RGBA pixels
+unsafe fn read_texture_layer(
+    material: Material,
+    handle: u32,
+    layer: u32,
+) -> Option<(usize, usize, Vec<u8>)> {
+    let target = match material {
+        Material::Textured => gl::TEXTURE_2D,
+        Material::LayeredTexture | Material::Outline => gl::TEXTURE_2D_ARRAY,
+        _ => return None,
+    };
+    // Query the texture dimensions
+    let (mut width, mut height, mut depth) = (0, 0, 0);
+    gl::BindTexture(target, handle);
+    gl::GetTexLevelParameteriv(target, 0, gl::TEXTURE_WIDTH, &mut width);
+    gl::GetTexLevelParameteriv(target, 0, gl::TEXTURE_HEIGHT, &mut height);
+    gl::GetTexLevelParameteriv(target, 0, gl::TEXTURE_DEPTH, &mut depth);
+    let (width, height, depth) = (width as usize, height as usize, depth.max(1) as usize);
+    if width == 0 || height == 0 || layer as usize >= depth {
+        gl::BindTexture(target, 0);
+        return None;
+    }
+    // Read back every layer, there is no way to read a single one in GL 3.3
+    let mut pixels = vec![0x0u8; width * height * depth * 4];
+    gl::GetTexImage(
+        target,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as *mut c_void,
+    );
+    gl::BindTexture(target, 0);
+    // Textures are uploaded bottom row first, so flip back to top-down
+    let layer_size = width * height * 4;
+    let start = layer as usize * layer_size;
+    let mut out = Vec::with_capacity(layer_size);
+    for row in pixels[start..start + layer_size].chunks(width * 4).rev() {
+        out.extend_from_slice(row);
+    }
+    Some((width, height, out))
+}
+
+/// Downscale an RGBA image (nearest neighbour) to fit within the thumbnail size
+fn thumbnail(width: usize, height: usize, pixels: &[u8]) -> (usize, usize, Vec<u8>) {
+    let scale = f32::max(width.max(height) as f32 / THUMBNAIL_SIZE as f32, 1.0);
+    let thumb_width = usize::max((width as f32 / scale) as usize, 1);
+    let thumb_height = usize::max((height as f32 / scale) as usize, 1);
+
+    let mut out = Vec::with_capacity(thumb_width * thumb_height * 4);
+    for y in 0..thumb_height {
+        let src_y = usize::min((y as f32 * scale) as usize, height - 1);
+        for x in 0..thumb_width {
+            let src_x = usize::min((x as f32 * scale) as usize, width - 1);
+            let index = (src_y * width + src_x) * 4;
+            out.extend_from_slice(&pixels[index..index + 4]);
+        }
+    }
+    (thumb_width, thumb_height, out)
+}
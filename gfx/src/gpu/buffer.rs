@@ -6,6 +6,8 @@ use gl::types::*;
 
 use crate::Bindable;
 
+use super::memory::{self, GpuAllocation, GpuResourceKind};
+
 /// Dynamic GPU buffer
 #[derive(Debug)]
 pub struct DynamicBuffer<T> {
@@ -37,6 +39,7 @@ where
             gl::BufferData(target, max_size, data_ptr, usage);
             handle
         };
+        memory::track(GpuAllocation::buffer(handle, size, size_of::<T>()));
         Self {
             handle,
             target,
@@ -49,6 +52,17 @@ where
         self.data.len()
     }
 
+    /// Get the pending buffer data
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Get the pending buffer data, mutably
+    /// NOTE: Must be flushed!
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
     /// Clear the pending buffer data
     /// NOTE: Must be flushed!
     pub fn clear(&mut self) {
@@ -106,6 +120,7 @@ impl<T> Bindable for DynamicBuffer<T> {
 
 impl<T> Drop for DynamicBuffer<T> {
     fn drop(&mut self) {
+        memory::untrack(GpuResourceKind::Buffer, self.handle);
         unsafe {
             gl::DeleteBuffers(1, &mut self.handle as *mut u32);
         }
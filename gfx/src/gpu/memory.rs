@@ -0,0 +1,123 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::Format;
+
+/// Global registry of every live GPU allocation
+/// NOTE: GL objects can only be created from the context thread, the mutex just keeps the static safe
+static REGISTRY: Mutex<Vec<GpuAllocation>> = Mutex::new(Vec::new());
+
+/// Type of a tracked GPU resource
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpuResourceKind {
+    Texture,
+    TextureArray,
+    Buffer,
+}
+
+/// Description of a single GPU allocation
+#[derive(Debug, Clone)]
+pub struct GpuAllocation {
+    pub kind: GpuResourceKind,
+    pub handle: u32,
+    // Dimensions of the resource, in (width, height, layers) form
+    // NOTE: Buffers are stored as (element count, 1, 1)
+    pub dimensions: (usize, usize, usize),
+    // Texel format, buffers don't have one
+    pub format: Option<Format>,
+    // Total size of the allocation, in bytes
+    pub bytes: usize,
+}
+
+impl GpuAllocation {
+    /// Describe a texture (or texture array) allocation
+    pub fn texture(
+        kind: GpuResourceKind,
+        handle: u32,
+        dimensions: (usize, usize, usize),
+        format: Format,
+    ) -> Self {
+        let (width, height, layers) = dimensions;
+        let bytes = width * height * layers * format.byte_size();
+        Self {
+            kind,
+            handle,
+            dimensions,
+            format: Some(format),
+            bytes,
+        }
+    }
+
+    /// Describe a buffer allocation
+    pub fn buffer(handle: u32, count: usize, element_size: usize) -> Self {
+        Self {
+            kind: GpuResourceKind::Buffer,
+            handle,
+            dimensions: (count, 1, 1),
+            format: None,
+            bytes: count * element_size,
+        }
+    }
+}
+
+impl fmt::Display for GpuAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height, layers) = self.dimensions;
+        write!(
+            f,
+            "{:?} #{}: {}x{}x{} {:?} ({} bytes)",
+            self.kind, self.handle, width, height, layers, self.format, self.bytes
+        )
+    }
+}
+
+/// Record a new GPU allocation
+pub(crate) fn track(allocation: GpuAllocation) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.push(allocation);
+    }
+}
+
+/// Remove a GPU allocation from the registry, once it has been freed
+pub(crate) fn untrack(kind: GpuResourceKind, handle: u32) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        if let Some(index) = registry
+            .iter()
+            .position(|a| a.kind == kind && a.handle == handle)
+        {
+            registry.swap_remove(index);
+        }
+    }
+}
+
+/// Get a snapshot of all live GPU allocations
+pub fn gpu_allocations() -> Vec<GpuAllocation> {
+    REGISTRY
+        .lock()
+        .map(|registry| registry.clone())
+        .unwrap_or_default()
+}
+
+/// Get the total number of bytes currently allocated on the GPU
+pub fn gpu_memory_used() -> usize {
+    gpu_allocations().iter().map(|a| a.bytes).sum()
+}
+
+/// Get the largest `count` live allocations, largest first
+pub fn top_gpu_allocations(count: usize) -> Vec<GpuAllocation> {
+    let mut allocations = gpu_allocations();
+    allocations.sort_by_key(|a| std::cmp::Reverse(a.bytes));
+    allocations.truncate(count);
+    allocations
+}
+
+/// Report any allocations that are still alive
+/// Should be called on shutdown, after all GPU resources have been dropped.
+/// Returns the number of leaked allocations
+pub fn report_gpu_leaks() -> usize {
+    let leaks = gpu_allocations();
+    for allocation in &leaks {
+        eprintln!("Leaked GPU allocation: {}", allocation);
+    }
+    leaks.len()
+}
@@ -1,9 +1,11 @@
 mod buffer;
+mod memory;
 mod pipeline;
 mod texture;
 mod types;
 
 pub use buffer::*;
+pub use memory::*;
 pub use pipeline::*;
 pub use texture::*;
 pub use types::*;
@@ -1,8 +1,12 @@
 use std::ffi::c_void;
 
+use anyhow::bail;
+
 use gl::types::*;
 
-use crate::{Bindable, Filtering, Format};
+use crate::{Bindable, Filtering, Format, Region};
+
+use super::memory::{self, GpuAllocation, GpuResourceKind};
 
 /// Texture object
 /// TODO: Utilize a texture queue/manager to allow for async texture creation
@@ -51,6 +55,12 @@ impl Texture {
 
             handle
         };
+        memory::track(GpuAllocation::texture(
+            GpuResourceKind::Texture,
+            handle,
+            (width, height, 1),
+            format,
+        ));
         Ok(Self {
             width,
             height,
@@ -79,6 +89,7 @@ impl Bindable for Texture {
 
 impl Drop for Texture {
     fn drop(&mut self) {
+        memory::untrack(GpuResourceKind::Texture, self.handle);
         unsafe { gl::DeleteTextures(1, &self.handle) }
     }
 }
@@ -89,6 +100,9 @@ pub struct TextureArray {
     pub height: usize,
     pub layers: usize,
     pub handle: u32,
+    // Area of each layer covered by its frame, for arrays built from differently sized frames
+    // Empty if every frame fills its layer
+    pub frames: Vec<Region>,
 }
 
 impl TextureArray {
@@ -147,14 +161,29 @@ impl TextureArray {
 
             handle
         };
+        memory::track(GpuAllocation::texture(
+            GpuResourceKind::TextureArray,
+            handle,
+            (width, height, layers),
+            format,
+        ));
         Ok(Self {
             width,
             height,
             layers,
             handle,
+            frames: Vec::new(),
         })
     }
 
+    /// Get the area of a layer covered by its frame, measured from the top-left of the layer
+    pub fn frame(&self, layer: usize) -> Region {
+        self.frames
+            .get(layer)
+            .copied()
+            .unwrap_or_else(|| Region::new(0.0, 0.0, self.width as f32, self.height as f32))
+    }
+
     /// Bind the texture to a texture slot
     /// NOTE: Shader bindings must be set to the texture slot index!
     pub fn bind_at(&self, index: u32) {
@@ -176,6 +205,146 @@ impl Bindable for TextureArray {
 
 impl Drop for TextureArray {
     fn drop(&mut self) {
+        memory::untrack(GpuResourceKind::TextureArray, self.handle);
         unsafe { gl::DeleteTextures(1, &self.handle) }
     }
 }
+
+/// Builder for texture arrays made from frames of differing sizes
+/// Each frame is centered within a layer large enough to hold the largest frame,
+/// and the frame's area is recorded so it can be placed correctly at draw time
+#[derive(Debug)]
+pub struct TextureArrayBuilder {
+    format: Format,
+    filtering: Filtering,
+    // Frames in (width, height, pixels) form
+    frames: Vec<(usize, usize, Vec<u8>)>,
+}
+
+impl TextureArrayBuilder {
+    /// Create a new, empty builder
+    pub fn new(format: Format, filtering: Filtering) -> Self {
+        Self {
+            format,
+            filtering,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Add a frame, returning its layer index
+    /// NOTE: Pixels must be in the same row order as any other texture upload (bottom row first)
+    pub fn push(&mut self, width: usize, height: usize, pixels: &[u8]) -> anyhow::Result<usize> {
+        if pixels.len() != width * height * self.format.byte_size() {
+            bail!(
+                "Frame pixel data does not match its dimensions ({}x{} {:?})",
+                width,
+                height,
+                self.format
+            );
+        }
+        self.frames.push((width, height, pixels.to_vec()));
+        Ok(self.frames.len() - 1)
+    }
+
+    /// Pad every frame to a common size and upload the texture array
+    pub fn build(self) -> anyhow::Result<TextureArray> {
+        if self.frames.is_empty() {
+            bail!("Texture arrays need at least one frame");
+        }
+        let layers = self.frames.len();
+        let (width, height, pixels, frames) = pack_frames(&self.frames, self.format.byte_size());
+
+        let mut array =
+            TextureArray::new(width, height, layers, self.format, self.filtering, &pixels)?;
+        array.frames = frames;
+        Ok(array)
+    }
+}
+
+/// Center each (width, height, pixels) frame in its own layer of a shared size
+/// Returns the layer size, the packed pixels for every layer and each frame's region
+fn pack_frames(
+    frames: &[(usize, usize, Vec<u8>)],
+    bpp: usize,
+) -> (usize, usize, Vec<u8>, Vec<Region>) {
+    let width = frames.iter().map(|(w, _, _)| *w).max().unwrap_or(0);
+    let height = frames.iter().map(|(_, h, _)| *h).max().unwrap_or(0);
+
+    let layer_size = width * height * bpp;
+    let mut pixels = vec![0x0u8; layer_size * frames.len()];
+    let mut regions = Vec::with_capacity(frames.len());
+    for (layer, (frame_width, frame_height, frame_pixels)) in frames.iter().enumerate() {
+        // Top-left corner of the centered frame
+        let x = (width - frame_width) / 2;
+        let y = (height - frame_height) / 2;
+        // Copy each row, counting from the bottom of the layer
+        let row_size = frame_width * bpp;
+        for row in 0..*frame_height {
+            let src = row * row_size;
+            let dst_row = height - y - frame_height + row;
+            let dst = layer * layer_size + (dst_row * width + x) * bpp;
+            pixels[dst..dst + row_size].copy_from_slice(&frame_pixels[src..src + row_size]);
+        }
+        regions.push(Region::new(
+            x as f32,
+            y as f32,
+            *frame_width as f32,
+            *frame_height as f32,
+        ));
+    }
+    (width, height, pixels, regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_frames() {
+        let frames = vec![
+            (4, 4, (1..=16).collect::<Vec<u8>>()),
+            (2, 1, vec![0xA, 0xB]),
+            (3, 2, vec![1, 2, 3, 4, 5, 6]),
+        ];
+        let (width, height, pixels, regions) = pack_frames(&frames, 1);
+        assert_eq!((width, height), (4, 4));
+        assert_eq!(pixels.len(), 4 * 4 * 3);
+        assert_eq!(
+            regions,
+            vec![
+                Region::new(0.0, 0.0, 4.0, 4.0),
+                Region::new(1.0, 1.0, 2.0, 1.0),
+                Region::new(0.0, 1.0, 3.0, 2.0),
+            ]
+        );
+
+        // The largest frame fills its layer unchanged
+        assert_eq!(&pixels[0..16], &frames[0].2[..]);
+        // Rows are stored bottom first, so a frame one row down from the top
+        // lands on the second-highest row of the layer
+        #[rustfmt::skip]
+        assert_eq!(&pixels[16..32], &[
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+            0, 0xA, 0xB, 0,
+            0, 0, 0, 0,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(&pixels[32..48], &[
+            0, 0, 0, 0,
+            1, 2, 3, 0,
+            4, 5, 6, 0,
+            0, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn test_pack_frames_multibyte() {
+        let frames = vec![(2, 2, vec![0u8; 2 * 2 * 2]), (1, 1, vec![7, 8])];
+        let (width, height, pixels, regions) = pack_frames(&frames, 2);
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(regions[1], Region::new(0.0, 0.0, 1.0, 1.0));
+        // A 1x1 frame at the top-left corner sits on the last (top) row
+        assert_eq!(&pixels[8..16], &[0, 0, 0, 0, 7, 8, 0, 0]);
+    }
+}
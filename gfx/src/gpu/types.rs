@@ -55,6 +55,19 @@ pub enum Format {
     R32g32b32a32_float,
 }
 
+impl Format {
+    /// Get the size, in bytes, of a single element of this format
+    pub fn byte_size(&self) -> usize {
+        match self {
+            Format::R8_uint => 1,
+            Format::R8g8b8a8_uint => 4,
+            Format::R32g32_float => 8,
+            Format::R32g32b32_float => 12,
+            Format::R32g32b32a32_float => 16,
+        }
+    }
+}
+
 /// OpenGL Vertex format
 /// (member count, type, normalized)
 pub type GLVertexFormat = (i32, GLenum, bool);
@@ -1,9 +1,13 @@
 mod batch;
+mod capture;
 mod gpu;
 mod material;
+mod png;
 mod util;
 
 pub use batch::*;
+pub use capture::*;
 pub use gpu::*;
 pub use material::*;
+pub use png::*;
 pub use util::*;
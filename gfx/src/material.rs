@@ -6,6 +6,8 @@ const VERTEX_SHADER_BASIC: &str = include_str!("shaders/basic.vert");
 const FRAGMENT_SHADER_COLOR: &str = include_str!("shaders/color.frag");
 const FRAGMENT_SHADER_TEXTURED: &str = include_str!("shaders/textured.frag");
 const FRAGMENT_SHADER_TEXTURED_ARRAY: &str = include_str!("shaders/textured_array.frag");
+const FRAGMENT_SHADER_OUTLINE: &str = include_str!("shaders/outline.frag");
+const FRAGMENT_SHADER_COLOR_EFFECT: &str = include_str!("shaders/color_effect.frag");
 
 /// Material type enums
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -16,6 +18,8 @@ pub enum Material {
     Textured,
     /// Textured geometry using a texture array
     LayeredTexture,
+    /// Solid color outline around the opaque pixels of a texture array layer
+    Outline,
 }
 
 /// Material map
@@ -26,6 +30,7 @@ pub struct MaterialMap {
     textured_array: Pipeline,
     color_lines: Pipeline,
     color_triangles: Pipeline,
+    outline: Pipeline,
 }
 
 impl MaterialMap {
@@ -42,12 +47,18 @@ impl MaterialMap {
         // Fragment shader for textured geometry
         let fs_textured = Shader::fragment(FRAGMENT_SHADER_TEXTURED, None)?;
         let fs_textured_array = Shader::fragment(FRAGMENT_SHADER_TEXTURED_ARRAY, None)?;
+        // Fragment shader for sprite outlines
+        let fs_outline = Shader::fragment(FRAGMENT_SHADER_OUTLINE, None)?;
+        // Per-draw color effect, linked into every pipeline
+        let fs_color_effect = Shader::fragment(FRAGMENT_SHADER_COLOR_EFFECT, None)?;
 
         // Shader list describing the colored geometry pipeline
-        let shaders_color = [&vs_basic, &fs_color];
+        let shaders_color = [&vs_basic, &fs_color, &fs_color_effect];
         // Shader list describing the textured geometry pipeline
-        let shaders_textured = [&vs_basic, &fs_textured];
-        let shaders_textured_array = [&vs_basic, &fs_textured_array];
+        let shaders_textured = [&vs_basic, &fs_textured, &fs_color_effect];
+        let shaders_textured_array = [&vs_basic, &fs_textured_array, &fs_color_effect];
+        // Shader list describing the outline pipeline
+        let shaders_outline = [&vs_basic, &fs_outline, &fs_color_effect];
 
         // Textured triangles pipeline
         // NOTE: It doesn't make much sense to have a line topoly version of this
@@ -57,12 +68,15 @@ impl MaterialMap {
         let color_lines = Pipeline::new(Topology::Lines, &shaders_color)?;
         // Colored triangles pipeline
         let color_triangles = Pipeline::new(Topology::Triangles, &shaders_color)?;
+        // Outlined sprites pipeline
+        let outline = Pipeline::new(Topology::Triangles, &shaders_outline)?;
 
         Ok(Self {
             textured,
             textured_array,
             color_lines,
             color_triangles,
+            outline,
         })
     }
 
@@ -73,6 +87,7 @@ impl MaterialMap {
             (Topology::Triangles, Material::Color) => Some(&self.color_triangles),
             (Topology::Triangles, Material::Textured) => Some(&self.textured),
             (Topology::Triangles, Material::LayeredTexture) => Some(&self.textured_array),
+            (Topology::Triangles, Material::Outline) => Some(&self.outline),
             _ => None,
         }
     }
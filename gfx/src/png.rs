@@ -0,0 +1,238 @@
+use std::io::{Result, Write};
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// Largest payload of a single uncompressed deflate block
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// Write an 8-bit RGBA image as a PNG
+/// Rows are expected top to bottom, 4 bytes per pixel.
+/// NOTE: The image data is stored uncompressed, this is meant for debug dumps rather than shipping assets
+pub fn write_png<W: Write>(out: &mut W, width: usize, height: usize, rgba: &[u8]) -> Result<()> {
+    assert_eq!(rgba.len(), width * height * 4);
+
+    out.write_all(&PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &header(width, height))?;
+    write_chunk(out, b"IDAT", &image_data(width, height, rgba))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Write 8-bit RGBA frames as an animated PNG, which loops forever
+/// Every frame is a full image in the same format as `write_png`, shown for its delay in milliseconds.
+/// Viewers without APNG support show the first frame.
+pub fn write_apng<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    frames: &[(&[u8], u16)],
+) -> Result<()> {
+    assert!(!frames.is_empty(), "Animated PNGs need at least one frame");
+
+    out.write_all(&PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &header(width, height))?;
+    // Animation control chunk: frame count, and 0 plays to loop forever
+    let mut control = Vec::with_capacity(8);
+    control.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+    control.extend_from_slice(&0u32.to_be_bytes());
+    write_chunk(out, b"acTL", &control)?;
+    // Frame control and frame data chunks share one sequence of numbers
+    let mut sequence = 0u32;
+    for (index, (rgba, delay)) in frames.iter().enumerate() {
+        assert_eq!(rgba.len(), width * height * 4);
+        // Frame control chunk: full size frame at the origin, delay as a fraction of a second,
+        // and no disposal or blending, since every frame replaces the whole image
+        let mut frame = Vec::with_capacity(26);
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(&(width as u32).to_be_bytes());
+        frame.extend_from_slice(&(height as u32).to_be_bytes());
+        frame.extend_from_slice(&[0; 8]);
+        frame.extend_from_slice(&delay.to_be_bytes());
+        frame.extend_from_slice(&1000u16.to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        write_chunk(out, b"fcTL", &frame)?;
+        sequence += 1;
+        // The first frame is the default image, the others are stored like it after a sequence number
+        let data = image_data(width, height, rgba);
+        if index == 0 {
+            write_chunk(out, b"IDAT", &data)?;
+        } else {
+            let mut frame_data = Vec::with_capacity(data.len() + 4);
+            frame_data.extend_from_slice(&sequence.to_be_bytes());
+            frame_data.extend_from_slice(&data);
+            write_chunk(out, b"fdAT", &frame_data)?;
+            sequence += 1;
+        }
+    }
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Header chunk data: dimensions, 8 bits per channel, color type 6 (RGBA), no interlacing
+fn header(width: usize, height: usize) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    header
+}
+
+/// Image data of an RGBA image, as stored in IDAT chunks
+fn image_data(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    // Each scanline is prefixed with its filter type (0, none)
+    let pitch = width * 4;
+    let mut scanlines = Vec::with_capacity((pitch + 1) * height);
+    for row in rgba.chunks(pitch.max(1)).take(height) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+    zlib_stored(&scanlines)
+}
+
+/// Write a single length-prefixed, CRC-suffixed PNG chunk
+fn write_chunk<W: Write>(out: &mut W, tag: &[u8; 4], data: &[u8]) -> Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(tag)?;
+    out.write_all(data)?;
+    let crc = crc32(crc32_update(0xFFFFFFFF, tag), data);
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// Wrap data in a zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = (data.len() / MAX_STORED_BLOCK) + 1;
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    // zlib header: deflate with a 32K window, no preset dictionary, fastest compression
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        // An empty stream still needs a single, final block
+        out.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let is_final = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Continue a CRC-32 (ISO-HDLC) over some bytes, without the final inversion
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    crc
+}
+
+/// Finish a CRC-32 over the given bytes
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    !crc32_update(crc, data)
+}
+
+/// Adler-32 checksum, as used by zlib
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split a PNG file into its chunks, checking every chunk's CRC
+    fn read_chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(PNG_SIGNATURE, png[..8]);
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let tag: [u8; 4] = rest[4..8].try_into().unwrap();
+            let data = rest[8..8 + len].to_vec();
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(crc32(crc32_update(0xFFFFFFFF, &tag), &data), crc);
+            chunks.push((tag, data));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    /// Decode a zlib stream made of stored deflate blocks, checking its checksum
+    fn unzlib_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!([0x78, 0x01], stream[..2]);
+        let mut out = Vec::new();
+        let mut rest = &stream[2..];
+        loop {
+            let is_final = rest[0] & 1 == 1;
+            // Only stored blocks (type 0) are written
+            assert_eq!(0, rest[0] >> 1);
+            let len = u16::from_le_bytes([rest[1], rest[2]]);
+            let nlen = u16::from_le_bytes([rest[3], rest[4]]);
+            assert_eq!(!len, nlen);
+            out.extend_from_slice(&rest[5..5 + len as usize]);
+            rest = &rest[5 + len as usize..];
+            if is_final {
+                break;
+            }
+        }
+        assert_eq!(adler32(&out).to_be_bytes(), rest);
+        out
+    }
+
+    #[test]
+    fn test_checksums() {
+        // Every PNG ends with the same IEND chunk, and so the same CRC
+        assert_eq!(0xAE426082, crc32(0xFFFFFFFF, b"IEND"));
+        assert_eq!(0x11E60398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(&[]));
+    }
+
+    #[test]
+    fn test_stored_blocks() {
+        let data: Vec<u8> = (0..MAX_STORED_BLOCK * 2 + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let stream = zlib_stored(&data);
+        // 3 blocks, each with a 5 byte header, plus the zlib header and checksum
+        assert_eq!(data.len() + 3 * 5 + 6, stream.len());
+        assert_eq!(0, stream[2]);
+        assert_eq!(1, stream[2 + 5 + MAX_STORED_BLOCK + 5 + MAX_STORED_BLOCK]);
+        assert_eq!(data, unzlib_stored(&stream));
+        assert!(unzlib_stored(&zlib_stored(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_write_png() {
+        let (width, height) = (3, 2);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| i as u8 * 7).collect();
+        let mut png = Vec::new();
+        write_png(&mut png, width, height, &rgba).unwrap();
+
+        let chunks = read_chunks(&png);
+        let tags: Vec<&[u8; 4]> = chunks.iter().map(|(tag, _)| tag).collect();
+        assert_eq!(vec![b"IHDR", b"IDAT", b"IEND"], tags);
+        // 3x2, 8 bits per channel, RGBA, default compression/filtering, no interlacing
+        assert_eq!(vec![0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0], chunks[0].1);
+        // Every scanline has a filter byte (0, none) in front of its pixels
+        let scanlines = unzlib_stored(&chunks[1].1);
+        let pitch = width * 4;
+        assert_eq!((pitch + 1) * height, scanlines.len());
+        let mut pixels = Vec::new();
+        for row in scanlines.chunks(pitch + 1) {
+            assert_eq!(0, row[0]);
+            pixels.extend_from_slice(&row[1..]);
+        }
+        assert_eq!(rgba, pixels);
+    }
+}
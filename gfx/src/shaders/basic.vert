@@ -4,11 +4,13 @@
 layout(location=0) in vec2 i_pos;
 layout(location=1) in vec3 i_uv;
 layout(location=2) in vec4 i_col;
+layout(location=3) in vec2 i_fx;
 
 out VertexData 
 {
     vec3 uv;
     vec4 col;
+    vec2 fx;
 } vs_out;
 
 layout (std140) uniform Matrices
@@ -20,5 +22,6 @@ void main()
 {
     vs_out.uv = i_uv;
     vs_out.col = i_col;
+    vs_out.fx = i_fx;
     gl_Position = u_projection*vec4(i_pos, 0.0, 1.0);
 }
@@ -0,0 +1,10 @@
+#version 330 core
+
+// Apply the per-draw (saturation, brightness) color effect
+// NOTE: Compiled once and linked into every pipeline, the other fragment shaders only declare it
+vec4 color_effect(vec4 color, vec2 fx)
+{
+    float luma = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    vec3 rgb = mix(vec3(luma), color.rgb, fx.x) * fx.y;
+    return vec4(rgb, color.a);
+}
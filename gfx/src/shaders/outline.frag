@@ -0,0 +1,45 @@
+#version 330 core
+#extension GL_ARB_separate_shader_objects : enable
+
+in VertexData
+{
+    vec3 uv;
+    vec4 col;
+    vec2 fx;
+} fs_in;
+
+layout(location=0) out vec4 o_frag;
+
+uniform sampler2DArray u_texture;
+
+// Apply the per-draw (saturation, brightness) color effect, defined in color_effect.frag
+vec4 color_effect(vec4 color, vec2 fx);
+
+// Get the alpha of the layer at a point, treating everything outside of it as transparent
+float alpha_at(vec2 uv)
+{
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))))
+    {
+        return 0.0;
+    }
+    return texture(u_texture, vec3(uv, fs_in.uv.z)).a;
+}
+
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(u_texture, 0).xy);
+    vec2 uv = fs_in.uv.xy;
+    // Only transparent pixels next to an opaque one are part of the outline
+    if (alpha_at(uv) > 0.0)
+    {
+        discard;
+    }
+    float edge = max(
+        max(alpha_at(uv + vec2(texel.x, 0.0)), alpha_at(uv - vec2(texel.x, 0.0))),
+        max(alpha_at(uv + vec2(0.0, texel.y)), alpha_at(uv - vec2(0.0, texel.y))));
+    if (edge <= 0.0)
+    {
+        discard;
+    }
+    o_frag = color_effect(fs_in.col, fs_in.fx);
+}
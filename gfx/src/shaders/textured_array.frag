@@ -5,13 +5,17 @@ in VertexData
 {
     vec3 uv;
     vec4 col;
+    vec2 fx;
 } fs_in;
 
 layout(location=0) out vec4 o_frag;
 
 uniform sampler2DArray u_texture;
 
+// Apply the per-draw (saturation, brightness) color effect, defined in color_effect.frag
+vec4 color_effect(vec4 color, vec2 fx);
+
 void main()
 {
-    o_frag = fs_in.col * texture(u_texture, fs_in.uv);
+    o_frag = color_effect(fs_in.col * texture(u_texture, fs_in.uv), fs_in.fx);
 }
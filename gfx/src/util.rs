@@ -40,89 +40,155 @@ impl Mul<Vector2<f32>> for Xform2D {
     }
 }
 
-/// NOTE: These two functions use old-school pointer arithmetic and unchecked de-referencing
-/// to achieve the best blit performance possible.
-
-/// Blit a RGB paletted image into an RGBA output buffer
-pub unsafe fn blit_with_palette(
-    width: usize,
-    height: usize,
-    pallete: &[u8; 256 * 3],
-    src: &[u8],
-    dst: &mut [u8],
-) {
-    let bpp = 4;
-    let src_pitch = width;
-    let dst_pitch = width * bpp;
-
-    let min_y = 0usize;
-    let max_y = height;
-
-    let min_x = 0usize;
-    let max_x = width;
-
-    let mut dst_row = dst[(min_x * bpp + min_y * dst_pitch)..].as_mut_ptr();
-    let mut src_row = src[(height - 1) * src_pitch..].as_ptr();
-    for _y in min_y..max_y {
-        let mut dst_pixel = dst_row;
-        let mut src_pixel = src_row;
-        for _x in min_x..max_x {
-            let value = *src_pixel as usize;
-
-            *dst_pixel.add(0) = *pallete.get_unchecked(value * 3 + 0);
-            *dst_pixel.add(1) = *pallete.get_unchecked(value * 3 + 1);
-            *dst_pixel.add(2) = *pallete.get_unchecked(value * 3 + 2);
-            *dst_pixel.add(3) = 0xFF;
-
-            dst_pixel = dst_pixel.add(bpp);
-            src_pixel = src_pixel.add(1);
+/// Pixel-space rectangle, measured from the top-left corner
+/// Used to select a portion of a texture to draw
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Region {
+    /// Create a new region from a top-left corner and a size
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+/// Point of a sprite that is placed at its transform's position
+/// Diablo sprites are anchored at their feet, UI art is usually centered
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    Center,
+    TopLeft,
+    BottomCenter,
+}
+
+impl Anchor {
+    /// Get the anchor point of a sprite, measured in pixels from its top-left corner
+    pub fn origin(self, width: f32, height: f32) -> Vector2<f32> {
+        match self {
+            Anchor::Center => vec2(width * 0.5, height * 0.5),
+            Anchor::TopLeft => vec2(0.0, 0.0),
+            Anchor::BottomCenter => vec2(width * 0.5, height),
         }
-        dst_row = dst_row.add(dst_pitch);
-        src_row = src_row.sub(src_pitch);
     }
 }
 
-/// Blit a RGB paletted image into an RGBA output buffer, with a transparency index
-pub unsafe fn blit_with_palette_and_transparency(
-    width: usize,
-    height: usize,
-    palette: &[u8; 256 * 3],
-    transparency: u8,
-    src: &[u8],
-    dst: &mut [u8],
-) {
-    let bpp = 4;
-    let src_pitch = width;
-    let dst_pitch = width * bpp;
-
-    let min_y = 0usize;
-    let max_y = height;
-
-    let min_x = 0usize;
-    let max_x = width;
-
-    let mut dst_row = dst[(min_x * bpp + min_y * dst_pitch)..].as_mut_ptr();
-    let mut src_row = src[(height - 1) * src_pitch..].as_ptr();
-    for _y in min_y..max_y {
-        let mut dst_pixel = dst_row;
-        let mut src_pixel = src_row;
-        for _x in min_x..max_x {
-            let value = *src_pixel as usize;
-            let mask = if value == transparency as usize {
-                0x00
-            } else {
-                0xFF
-            };
-
-            *dst_pixel.add(0) = mask & palette.get_unchecked(value * 3 + 0);
-            *dst_pixel.add(1) = mask & palette.get_unchecked(value * 3 + 1);
-            *dst_pixel.add(2) = mask & palette.get_unchecked(value * 3 + 2);
-            *dst_pixel.add(3) = mask & 0xFF;
-
-            dst_pixel = dst_pixel.add(bpp);
-            src_pixel = src_pixel.add(1);
+/// Area of the window the game is rendered to, letterboxed to keep the aspect ratio
+/// NOTE: Measured in framebuffer pixels from the bottom-left corner, like GL viewports
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Viewport {
+    /// Calculate the viewport from a given window dimension and a desired aspect ratio
+    pub fn from_window(aspect_ratio: f32, window_size: (i32, i32)) -> Self {
+        let (width, height) = window_size;
+        let mut w = width;
+        let mut h = (w as f32 / aspect_ratio + 0.5f32) as i32;
+        if h > height {
+            h = height;
+            w = (height as f32 * aspect_ratio + 0.5f32) as i32;
         }
-        dst_row = dst_row.add(dst_pitch);
-        src_row = src_row.sub(src_pitch);
+        let x = (width - w) / 2;
+        let y = (height - h) / 2;
+        Self { x, y, w, h }
+    }
+
+    /// Convert a framebuffer position (measured from the top-left) to virtual screen coordinates
+    pub fn to_virtual(
+        &self,
+        pos: (f32, f32),
+        framebuffer_height: i32,
+        render_size: (u32, u32),
+    ) -> (f32, f32) {
+        // The viewport is measured from the bottom of the framebuffer
+        let top = framebuffer_height - self.y - self.h;
+        let x = (pos.0 - self.x as f32) / self.w.max(1) as f32 * render_size.0 as f32;
+        let y = (pos.1 - top as f32) / self.h.max(1) as f32 * render_size.1 as f32;
+        (x, y)
+    }
+}
+
+/// Projection from virtual screen coordinates (measured in pixels from the top-left) to a window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Projection {
+    pub matrix: Matrix4<f32>,
+    pub viewport: Viewport,
+    pub render_size: (u32, u32),
+}
+
+impl Projection {
+    /// Create a projection showing the whole virtual screen, as large as the window allows
+    /// NOTE: The virtual screen is scaled by any factor that fits, pixel snapping the batch keeps
+    /// art crisp at non-integer scales
+    pub fn pixel_perfect(render_size: (u32, u32), window_size: (i32, i32)) -> Self {
+        let (width, height) = (render_size.0 as f32, render_size.1 as f32);
+        Self {
+            matrix: ortho(0.0, width, height, 0.0, -1.0, 1.0),
+            viewport: Viewport::from_window(width / height, window_size),
+            render_size,
+        }
+    }
+
+    /// Convert a framebuffer position (measured from the top-left) to virtual screen coordinates
+    pub fn to_virtual(&self, pos: (f32, f32), framebuffer_height: i32) -> (f32, f32) {
+        self.viewport
+            .to_virtual(pos, framebuffer_height, self.render_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_letterbox() {
+        // Wider windows are pillarboxed, taller windows are letterboxed
+        let viewport = Viewport::from_window(4.0 / 3.0, (1920, 1080));
+        assert_eq!(
+            Viewport {
+                x: 240,
+                y: 0,
+                w: 1440,
+                h: 1080
+            },
+            viewport
+        );
+        let viewport = Viewport::from_window(4.0 / 3.0, (640, 600));
+        assert_eq!(
+            Viewport {
+                x: 0,
+                y: 60,
+                w: 640,
+                h: 480
+            },
+            viewport
+        );
+    }
+
+    #[test]
+    fn test_pixel_perfect() {
+        let projection = Projection::pixel_perfect((640, 480), (1920, 1080));
+        // The corners of the virtual screen are the corners of clip space
+        let top_left = projection.matrix * vec4(0.0, 0.0, 0.0, 1.0);
+        let bottom_right = projection.matrix * vec4(640.0, 480.0, 0.0, 1.0);
+        assert!((top_left - vec4(-1.0, 1.0, 0.0, 1.0)).magnitude() < 1e-6);
+        assert!((bottom_right - vec4(1.0, -1.0, 0.0, 1.0)).magnitude() < 1e-6);
+        // ...and the cursor maps back to the same corners
+        assert_eq!((0.0, 0.0), projection.to_virtual((240.0, 0.0), 1080));
+        assert_eq!(
+            (640.0, 480.0),
+            projection.to_virtual((1680.0, 1080.0), 1080)
+        );
+        assert_eq!((320.0, 240.0), projection.to_virtual((960.0, 540.0), 1080));
     }
 }
@@ -1,10 +1,15 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io::{Error, ErrorKind, Result};
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::Instant;
 
 use super::crypto::HashType;
 use super::header::*;
+use super::key::{AssetKey, PathHashes};
+use super::path::ArchivePath;
+use super::trace::{Access, AccessTrace};
 use super::{compression, crypto};
 
 /// MPQ data archive
@@ -24,6 +29,8 @@ pub struct Archive {
     // Lookup tables for files
     hash_table: Vec<HashEntry>,
     block_table: Vec<BlockEntry>,
+    // Log of file accesses, if tracing is enabled
+    trace: Option<AccessTrace>,
 }
 
 impl Archive {
@@ -61,24 +68,64 @@ impl Archive {
             header,
             hash_table,
             block_table,
+            trace: None,
         })
     }
 
+    /// Log every file read from the archive to a trace, or stop logging with None
+    pub fn set_access_trace(&mut self, trace: Option<AccessTrace>) {
+        self.trace = trace;
+    }
+
     /// Check if a file exists in the archive
-    pub fn has_file(&self, filename: &str) -> bool {
-        self.get_block_index(filename).is_some()
+    pub fn has_file<P: Into<ArchivePath>>(&self, path: P) -> bool {
+        self.get_block_index(&PathHashes::new(&path.into()))
+            .is_some()
+    }
+
+    /// Check if a file exists in the archive, using the key's precomputed hashes
+    pub fn has_file_by_key(&self, key: AssetKey) -> bool {
+        self.get_block_index(&key.hashes()).is_some()
     }
 
     /// Get the handle for a file in the archive
-    pub fn get_file(&self, filename: &str) -> Result<File> {
+    pub fn get_file<P: Into<ArchivePath>>(&self, path: P) -> Result<File<'_>> {
+        let path = path.into();
+        // Traced files are interned to keep their handles copyable
+        let traced = self.trace.as_ref().map(|_| AssetKey::new(&path));
+        self.get_file_by_hashes(&PathHashes::new(&path), traced)
+    }
+
+    /// Get the handle for a file in the archive, using the key's precomputed hashes
+    pub fn get_file_by_key(&self, key: AssetKey) -> Result<File<'_>> {
+        let traced = self.trace.as_ref().map(|_| key);
+        self.get_file_by_hashes(&key.hashes(), traced)
+    }
+
+    fn get_file_by_hashes(
+        &self,
+        hashes: &PathHashes,
+        traced: Option<AssetKey>,
+    ) -> Result<File<'_>> {
         // Get the block
         let block = self
-            .get_block_index(filename)
-            .and_then(|index| Some(&self.block_table[index as usize]))
+            .get_block_index(hashes)
+            .and_then(|index| self.block_table.get(index))
             .ok_or(Error::new(
                 ErrorKind::NotFound,
                 "Failed to get block for file",
-            ))?;
+            ));
+        if let (Err(_), Some(trace), Some(key)) = (&block, &self.trace, traced) {
+            trace.record(&Access {
+                path: key.path(),
+                method: "missing",
+                size_packed: 0,
+                size_unpacked: 0,
+                bytes_read: 0,
+                duration: Default::default(),
+            });
+        }
+        let block = block?;
         if !block.exists() {
             return Err(Error::new(
                 ErrorKind::NotFound,
@@ -87,13 +134,7 @@ impl Archive {
         }
         // If the file is encrypted, get the encryption key
         let file_key = if block.is_encrypted() {
-            // Get the last portion of the filename, without the path info
-            let filename = filename.split(&['\\', '/'][..]).last().ok_or(Error::new(
-                ErrorKind::InvalidData,
-                "Failed to extract filename from path",
-            ))?;
-            // Hash the filename as the encryption key
-            Some(crypto::hash(filename, HashType::FileKey))
+            Some(hashes.file_key)
         } else {
             None
         };
@@ -102,35 +143,111 @@ impl Archive {
             key: file_key,
             block: *block,
             archive: self,
+            traced,
         })
     }
 
-    fn get_block_index(&self, filename: &str) -> Option<usize> {
-        // Hash the filename into a couple of different markers
-        let hash_i = crypto::hash(filename, HashType::TableOffset);
-        let hash_a = crypto::hash(filename, HashType::NameA);
-        let hash_b = crypto::hash(filename, HashType::NameB);
-        // Get the length of the hashtable
-        let len = self.header.hash_table_count - 1;
-        // Get the starting index based on the hash function
-        let start_index = hash_i & len;
-        // Linear probing
-        let mut i = start_index;
-        loop {
-            // If the hashes match, return the table index
-            let hash = &self.hash_table[i as usize];
-            if hash.hash_a == hash_a && hash.hash_b == hash_b {
-                return Some(hash.block_index as usize);
-            }
-            // No match found, iterate
-            i = (i + 1) & len;
-            // If this block index was marked as 'never used',
-            // or we're back at the starting index, return None
-            if hash.block_index == BLOCK_INDEX_FREE || i == start_index {
-                return None;
+    /// Get the number of entries in the block table, including unused ones
+    pub fn block_count(&self) -> usize {
+        self.block_table.len()
+    }
+
+    /// Get the metadata of a block table entry
+    pub fn file_info(&self, block_index: usize) -> Option<FileInfo> {
+        self.block_table.get(block_index).map(|block| FileInfo {
+            block_index,
+            block: *block,
+        })
+    }
+
+    /// Get the handle for a file by its block table index
+    /// Used for entries that no known name resolves to. Encrypted files can't be read this way,
+    /// as their key is hashed from the (unknown) file name
+    pub fn get_file_by_index(&self, block_index: usize) -> Result<File<'_>> {
+        let block = self.block_table.get(block_index).ok_or(Error::new(
+            ErrorKind::NotFound,
+            "Block index is out of range",
+        ))?;
+        if !block.exists() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                "File block marked as non-existant",
+            ));
+        }
+        if block.is_encrypted() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Encrypted files can't be read without their name",
+            ));
+        }
+        Ok(File {
+            key: None,
+            block: *block,
+            archive: self,
+            traced: None,
+        })
+    }
+
+    /// Check a list of candidate filenames against the archive, returning every name that exists
+    /// Used to rebuild listfiles for archives that don't ship one
+    pub fn resolve_names<'n, I>(&self, candidates: I) -> Vec<&'n str>
+    where
+        I: IntoIterator<Item = &'n str>,
+    {
+        candidates
+            .into_iter()
+            .filter(|name| self.has_file(*name))
+            .collect()
+    }
+
+    /// Count the files in the archive that none of the known names resolve to
+    pub fn count_unnamed(&self, known: &[&str]) -> usize {
+        let known: HashSet<(u32, u32)> = known
+            .iter()
+            .map(|name| {
+                let path = ArchivePath::new(name);
+                let hash_a = crypto::hash(path.as_str(), HashType::NameA);
+                let hash_b = crypto::hash(path.as_str(), HashType::NameB);
+                (hash_a, hash_b)
+            })
+            .collect();
+        self.hash_table
+            .iter()
+            // Skip free and deleted entries, which don't point at a block
+            .filter(|hash| (hash.block_index as usize) < self.block_table.len())
+            .filter(|hash| !known.contains(&(hash.hash_a, hash.hash_b)))
+            .count()
+    }
+
+    fn get_block_index(&self, hashes: &PathHashes) -> Option<usize> {
+        probe_hash_table(&self.hash_table, hashes)
+    }
+}
+
+/// Find the block index of a path in a hash table
+/// Lookups start at the entry the offset hash points to, and probe linearly (wrapping around)
+/// until a matching entry is found, a free entry ends the chain, or every entry has been checked.
+/// Deleted entries still continue the chain, but never match
+fn probe_hash_table(hash_table: &[HashEntry], hashes: &PathHashes) -> Option<usize> {
+    // The table size is a power of two in every known archive, but a modulo doesn't rely on it
+    let len = hash_table.len();
+    if len == 0 {
+        return None;
+    }
+    let start_index = hashes.offset as usize % len;
+    for offset in 0..len {
+        let hash = &hash_table[(start_index + offset) % len];
+        match hash.block_index {
+            // Never used, so nothing was ever stored further along the chain
+            BLOCK_INDEX_FREE => return None,
+            BLOCK_INDEX_DELETED => continue,
+            block_index if hash.hash_a == hashes.name_a && hash.hash_b == hashes.name_b => {
+                return Some(block_index as usize)
             }
+            _ => {}
         }
     }
+    None
 }
 
 impl std::fmt::Debug for Archive {
@@ -144,12 +261,64 @@ impl std::fmt::Debug for Archive {
     }
 }
 
+/// Metadata of a file stored in a MPQ Archive
+#[derive(Debug, Clone, Copy)]
+pub struct FileInfo {
+    block_index: usize,
+    block: BlockEntry,
+}
+
+impl FileInfo {
+    /// Get the index of the file in the block table
+    pub fn block_index(&self) -> usize {
+        self.block_index
+    }
+
+    /// Get the byte offset of the file from the start of the archive
+    pub fn offset(&self) -> usize {
+        self.block.offset as usize
+    }
+
+    /// Get the size of the file as stored, in bytes
+    pub fn size_packed(&self) -> usize {
+        self.block.size_packed as usize
+    }
+
+    /// Get the size of the file once decompressed, in bytes
+    pub fn size_unpacked(&self) -> usize {
+        self.block.size_unpacked as usize
+    }
+
+    /// Get the raw block flags
+    pub fn flags(&self) -> u32 {
+        self.block.flags.bits()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.block.exists()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.block.is_compressed()
+    }
+
+    pub fn is_imploded(&self) -> bool {
+        self.block.is_imploded()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.block.is_encrypted()
+    }
+}
+
 /// A handle pointing to a file stored in a MPQ Archive
 #[derive(Debug, Clone, Copy)]
 pub struct File<'a> {
     key: Option<u32>,
     block: BlockEntry,
     archive: &'a Archive,
+    // Path of the file, if the archive's accesses are being traced
+    traced: Option<AssetKey>,
 }
 
 impl<'a> File<'a> {
@@ -162,6 +331,37 @@ impl<'a> File<'a> {
     /// NOTE: This matches the std::io::Read, but cannot be implemented directly.
     /// This is because the whole file must be read at once, due to the way compression and encryption work.
     pub fn read(&self, out: &mut [u8]) -> Result<usize> {
+        let (trace, key) = match (&self.archive.trace, self.traced) {
+            (Some(trace), Some(key)) => (trace, key),
+            _ => return self.read_untraced(out),
+        };
+        let start = Instant::now();
+        let result = self.read_untraced(out);
+        trace.record(&Access {
+            path: key.path(),
+            method: self.method(),
+            size_packed: self.block.size_packed as usize,
+            size_unpacked: self.block.size_unpacked as usize,
+            bytes_read: *result.as_ref().unwrap_or(&0),
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    /// Get how the file is stored, as logged in access traces
+    fn method(&self) -> &'static str {
+        if !self.block.is_compressed() {
+            "stored"
+        } else if self.block.is_imploded() {
+            "implode"
+        } else if self.block.has_muli_compression() {
+            "multi"
+        } else {
+            "copy"
+        }
+    }
+
+    fn read_untraced(&self, out: &mut [u8]) -> Result<usize> {
         // Check that the file can be read into the supplied output buffer
         if out.len() < self.size() {
             return Err(Error::new(
@@ -221,3 +421,141 @@ impl<'a> File<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator, so every run tests the same tables
+    struct Rng(u32);
+
+    impl Rng {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn below(&mut self, max: usize) -> usize {
+            self.next() as usize % max
+        }
+    }
+
+    fn free_entry() -> HashEntry {
+        HashEntry {
+            hash_a: BLOCK_INDEX_FREE,
+            hash_b: BLOCK_INDEX_FREE,
+            locale: 0xFFFF,
+            platform: 0xFFFF,
+            block_index: BLOCK_INDEX_FREE,
+        }
+    }
+
+    fn random_hashes(rng: &mut Rng) -> PathHashes {
+        PathHashes {
+            offset: rng.next(),
+            name_a: rng.next(),
+            name_b: rng.next(),
+            file_key: 0,
+        }
+    }
+
+    /// Store a path the way archive tools do, in the first unused entry of its chain
+    fn insert(table: &mut [HashEntry], hashes: &PathHashes, block_index: u32) -> usize {
+        let len = table.len();
+        let start_index = hashes.offset as usize % len;
+        let index = (0..len)
+            .map(|offset| (start_index + offset) % len)
+            .find(|&index| {
+                matches!(
+                    table[index].block_index,
+                    BLOCK_INDEX_FREE | BLOCK_INDEX_DELETED
+                )
+            })
+            .expect("Hash table is full");
+        table[index] = HashEntry {
+            hash_a: hashes.name_a,
+            hash_b: hashes.name_b,
+            locale: 0,
+            platform: 0,
+            block_index,
+        };
+        index
+    }
+
+    #[test]
+    fn test_probe_collisions() {
+        let mut table: Vec<HashEntry> = (0..8).map(|_| free_entry()).collect();
+        // Every path starts at the last entry, so the chain wraps around to the start
+        let paths: Vec<PathHashes> = (0..3)
+            .map(|i| PathHashes {
+                offset: 7 + 8 * i,
+                name_a: i,
+                name_b: 100 + i,
+                file_key: 0,
+            })
+            .collect();
+        for (block_index, path) in paths.iter().enumerate() {
+            insert(&mut table, path, block_index as u32);
+        }
+        for (block_index, path) in paths.iter().enumerate() {
+            assert_eq!(Some(block_index), probe_hash_table(&table, path));
+        }
+        // Deleting the middle of the chain keeps the rest of it reachable
+        table[0].block_index = BLOCK_INDEX_DELETED;
+        assert_eq!(None, probe_hash_table(&table, &paths[1]));
+        assert_eq!(Some(2), probe_hash_table(&table, &paths[2]));
+        // A free entry whose hashes happen to match isn't a file
+        let free = PathHashes {
+            offset: 4,
+            name_a: BLOCK_INDEX_FREE,
+            name_b: BLOCK_INDEX_FREE,
+            file_key: 0,
+        };
+        assert_eq!(None, probe_hash_table(&table, &free));
+        assert_eq!(None, probe_hash_table(&[], &paths[0]));
+    }
+
+    #[test]
+    fn test_probe_random_tables() {
+        let mut rng = Rng(0x1234567);
+        for round in 0..200 {
+            // Include table sizes that aren't a power of two
+            let len = 1 + rng.below(40);
+            let mut table: Vec<HashEntry> = (0..len).map(|_| free_entry()).collect();
+            // Some rounds fill the table completely, leaving no free entry to end a chain
+            let count = if round % 4 == 0 {
+                len
+            } else {
+                rng.below(len + 1)
+            };
+            let mut stored: Vec<(PathHashes, usize, bool)> = Vec::new();
+            for block_index in 0..count {
+                let hashes = random_hashes(&mut rng);
+                let index = insert(&mut table, &hashes, block_index as u32);
+                stored.push((hashes, index, true));
+            }
+            // Delete some of them
+            for entry in stored.iter_mut() {
+                if rng.below(3) == 0 {
+                    table[entry.1].block_index = BLOCK_INDEX_DELETED;
+                    entry.2 = false;
+                }
+            }
+            for (block_index, (hashes, _, live)) in stored.iter().enumerate() {
+                let expected = if *live { Some(block_index) } else { None };
+                assert_eq!(
+                    expected,
+                    probe_hash_table(&table, hashes),
+                    "round {}",
+                    round
+                );
+            }
+            // Paths that were never stored aren't found, even in a full table
+            for _ in 0..8 {
+                assert_eq!(None, probe_hash_table(&table, &random_hashes(&mut rng)));
+            }
+        }
+    }
+}
@@ -141,7 +141,7 @@ bitflags! {
 /// Denotes that this hash entry is free (never used)
 pub const BLOCK_INDEX_FREE: u32 = 0xFFFFFFFF;
 /// Denotes that this hash entry has been deleted
-pub const _BLOCK_INDEX_DELETED: u32 = 0xFFFFFFFF;
+pub const BLOCK_INDEX_DELETED: u32 = 0xFFFFFFFE;
 
 /// Entry in the MPQ archive hash table
 #[derive(Debug)]
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+use super::crypto::{self, HashType};
+use super::path::ArchivePath;
+
+/// Interned archive path, with its archive lookup hashes computed once
+/// Keys are small integers, so they are cheap to copy, compare and hash in caches and manifests.
+/// NOTE: Interned paths live for the rest of the program, so keys are meant for asset paths,
+/// not arbitrary user input
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetKey(u32);
+
+/// Hashes used to find a path in an archive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathHashes {
+    pub offset: u32,
+    pub name_a: u32,
+    pub name_b: u32,
+    // Encryption key of the file, hashed from the file name without its directories
+    pub file_key: u32,
+}
+
+impl PathHashes {
+    pub fn new(path: &ArchivePath) -> Self {
+        Self {
+            offset: crypto::hash(path.as_str(), HashType::TableOffset),
+            name_a: crypto::hash(path.as_str(), HashType::NameA),
+            name_b: crypto::hash(path.as_str(), HashType::NameB),
+            file_key: crypto::hash(path.file_name(), HashType::FileKey),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Interner {
+    // Interned paths and their hashes, indexed by key
+    entries: Vec<(&'static str, PathHashes)>,
+    keys: HashMap<ArchivePath, AssetKey>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl AssetKey {
+    /// Get the key for a path, interning it if needed
+    /// Paths are normalized first, so every spelling of a path gets the same key
+    pub fn new<P: Into<ArchivePath>>(path: P) -> Self {
+        let path = path.into();
+        if let Some(key) = interner().read().unwrap().keys.get(&path) {
+            return *key;
+        }
+        let mut interner = interner().write().unwrap();
+        // Another thread may have interned the path between the locks
+        if let Some(key) = interner.keys.get(&path) {
+            return *key;
+        }
+        let key = AssetKey(interner.entries.len() as u32);
+        let hashes = PathHashes::new(&path);
+        let name: &'static str = Box::leak(path.as_str().into());
+        interner.entries.push((name, hashes));
+        interner.keys.insert(path, key);
+        key
+    }
+
+    /// Get the path of the key, as first interned
+    pub fn path(&self) -> &'static str {
+        interner().read().unwrap().entries[self.0 as usize].0
+    }
+
+    pub(crate) fn hashes(&self) -> PathHashes {
+        interner().read().unwrap().entries[self.0 as usize].1
+    }
+}
+
+impl fmt::Debug for AssetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AssetKey").field(&self.path()).finish()
+    }
+}
+
+impl fmt::Display for AssetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.path())
+    }
+}
+
+impl From<&str> for AssetKey {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&String> for AssetKey {
+    fn from(path: &String) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<AssetKey> for ArchivePath {
+    fn from(key: AssetKey) -> Self {
+        ArchivePath::new(key.path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interning() {
+        let key = AssetKey::new("ui_art\\title.pcx");
+        assert_eq!(key, AssetKey::new("UI_ART/Title.PCX"));
+        assert_ne!(key, AssetKey::new("ui_art\\logo.pcx"));
+        assert_eq!("ui_art\\title.pcx", key.path());
+
+        let hashes = key.hashes();
+        assert_eq!(
+            crypto::hash("UI_ART\\TITLE.PCX", HashType::NameA),
+            hashes.name_a
+        );
+        assert_eq!(
+            crypto::hash("title.pcx", HashType::FileKey),
+            hashes.file_key
+        );
+    }
+}
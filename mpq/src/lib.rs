@@ -2,8 +2,16 @@ mod archive;
 mod compression;
 mod crypto;
 mod header;
+mod key;
+mod listfile;
+mod path;
+mod trace;
 
 pub use archive::*;
+pub use key::*;
+pub use listfile::*;
+pub use path::*;
+pub use trace::*;
 
 #[cfg(test)]
 mod tests {
@@ -26,6 +34,21 @@ mod tests {
 		open_file("Levels\\TownData\\Sector1s.DUN").expect("Failed to open file");
 	}
 	
+	#[test]
+	fn test_get_file_by_index() {
+		let archive = Archive::open(ARCHIVE_PATH).expect("Failed to open archive");
+		let info = (0..archive.block_count())
+			.filter_map(|index| archive.file_info(index))
+			.find(|info| info.exists() && !info.is_encrypted())
+			.expect("No readable file blocks");
+		let file = archive
+			.get_file_by_index(info.block_index())
+			.expect("Failed to open file");
+		let mut bytes = vec![0x0u8; file.size()];
+		assert_eq!(info.size_unpacked(), file.read(&mut bytes).expect("Failed to read file"));
+		assert!(archive.get_file_by_index(archive.block_count()).is_err());
+	}
+
 	fn open_file(filename: &str) -> std::io::Result<Vec<u8>> {
 		let archive = Archive::open(ARCHIVE_PATH)?;
 		let file = archive.get_file(filename)?;
@@ -0,0 +1,66 @@
+/// Expand a filename pattern into every filename it describes
+/// Patterns can contain any number of brace groups, which are either alternatives (`{a,d,h}`)
+/// or inclusive numeric ranges (`{0..9}`). Ranges keep the width of their start, so `{00..15}`
+/// counts `00, 01, ... 15`.
+/// NOTE: Brace groups can't be nested, and unterminated braces are kept as-is
+pub fn expand_pattern(pattern: &str) -> Vec<String> {
+    let mut names = vec![String::new()];
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        let options = expand_group(&rest[open + 1..close]);
+        let prefix = &rest[..open];
+        names = names
+            .iter()
+            .flat_map(|name| {
+                options
+                    .iter()
+                    .map(move |option| format!("{}{}{}", name, prefix, option))
+            })
+            .collect();
+        rest = &rest[close + 1..];
+    }
+    for name in names.iter_mut() {
+        name.push_str(rest);
+    }
+    names
+}
+
+/// Expand the contents of a single brace group
+fn expand_group(group: &str) -> Vec<String> {
+    if let Some((start, end)) = group.split_once("..") {
+        if let (Ok(first), Ok(last)) = (start.parse::<u32>(), end.parse::<u32>()) {
+            let width = start.len();
+            return (first..=last)
+                .map(|value| format!("{:0width$}", value, width = width))
+                .collect();
+        }
+    }
+    group.split(',').map(|option| option.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_pattern() {
+        assert_eq!(vec!["Data\\Square.CEL"], expand_pattern("Data\\Square.CEL"));
+        assert_eq!(
+            vec!["sfx\\misc\\walk1.wav", "sfx\\misc\\walk2.wav"],
+            expand_pattern("sfx\\misc\\walk{1..2}.wav")
+        );
+        assert_eq!(
+            vec!["zombiea.cl2", "zombied.cl2", "zombiha.cl2", "zombihd.cl2"],
+            expand_pattern("zombi{e,h}{a,d}.cl2")
+        );
+        let levels = expand_pattern("Levels\\L{1..4}Data\\L{1..4}.TIL");
+        assert_eq!(16, levels.len());
+        assert_eq!("Levels\\L4Data\\L4.TIL", levels[15]);
+        assert_eq!(vec!["a08", "a09", "a10"], expand_pattern("a{08..10}"));
+        assert_eq!(vec!["broken{pattern"], expand_pattern("broken{pattern"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Separator used by paths stored in archives
+pub const ARCHIVE_SEPARATOR: char = '\\';
+
+/// Path to a file stored in an archive
+/// Forward slashes are converted to backslashes, and repeated or leading separators are dropped,
+/// so user-supplied and mod paths resolve the same way as listfile names.
+/// Comparisons ignore ASCII case like the archive hashes do, but the original case is kept for display
+#[derive(Debug, Clone)]
+pub struct ArchivePath(String);
+
+impl ArchivePath {
+    /// Normalize a path into an archive path
+    pub fn new(path: &str) -> Self {
+        let mut normalized = String::with_capacity(path.len());
+        for component in path.split(&['\\', '/'][..]) {
+            if component.is_empty() {
+                continue;
+            }
+            if !normalized.is_empty() {
+                normalized.push(ARCHIVE_SEPARATOR);
+            }
+            normalized.push_str(component);
+        }
+        Self(normalized)
+    }
+
+    /// Get the normalized path
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the last component of the path, without the directories
+    pub fn file_name(&self) -> &str {
+        self.0.rsplit(ARCHIVE_SEPARATOR).next().unwrap_or(&self.0)
+    }
+
+    /// Get the path to a file under this one
+    pub fn join(&self, path: &str) -> Self {
+        Self::new(&format!("{}{}{}", self.0, ARCHIVE_SEPARATOR, path))
+    }
+}
+
+impl fmt::Display for ArchivePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for ArchivePath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ArchivePath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&String> for ArchivePath {
+    fn from(path: &String) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<String> for ArchivePath {
+    fn from(path: String) -> Self {
+        Self::new(&path)
+    }
+}
+
+impl From<&ArchivePath> for ArchivePath {
+    fn from(path: &ArchivePath) -> Self {
+        path.clone()
+    }
+}
+
+impl PartialEq for ArchivePath {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for ArchivePath {}
+
+impl Hash for ArchivePath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for byte in self.0.bytes() {
+            state.write_u8(byte.to_ascii_uppercase());
+        }
+        // Terminate the path like str does, so paths can be part of compound keys
+        state.write_u8(0xFF);
+    }
+}
+
+impl PartialOrd for ArchivePath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ArchivePath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let a = self.0.bytes().map(|byte| byte.to_ascii_uppercase());
+        let b = other.0.bytes().map(|byte| byte.to_ascii_uppercase());
+        a.cmp(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let path = ArchivePath::new("/levels//towndata/Town.til");
+        assert_eq!("levels\\towndata\\Town.til", path.as_str());
+        assert_eq!("Town.til", path.file_name());
+        assert_eq!(ArchivePath::new("Levels\\TownData\\Town.TIL"), path);
+        assert_eq!(
+            ArchivePath::new("Data\\Square.CEL"),
+            ArchivePath::new("data").join("square.cel")
+        );
+        assert_eq!("", ArchivePath::new("").file_name());
+    }
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::io::{BufWriter, Result, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// CSV log of archive file accesses, for analysing asset access patterns
+/// Every file handle got from the archive is logged when read, along with lookups of missing files
+#[derive(Debug)]
+pub struct AccessTrace {
+    writer: Mutex<BufWriter<fs::File>>,
+    // Time the trace was started, which access times are relative to
+    start: Instant,
+}
+
+/// A single logged access
+#[derive(Debug)]
+pub(crate) struct Access<'a> {
+    pub path: &'a str,
+    // How the file is stored ("stored", "implode", "multi" or "copy"), or "missing"
+    pub method: &'a str,
+    pub size_packed: usize,
+    pub size_unpacked: usize,
+    // Bytes written by the read
+    pub bytes_read: usize,
+    pub duration: Duration,
+}
+
+impl AccessTrace {
+    /// Create a trace file, writing the CSV header
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        writeln!(
+            writer,
+            "time_s,path,method,packed_bytes,unpacked_bytes,read_bytes,read_us"
+        )?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+            start: Instant::now(),
+        })
+    }
+
+    pub(crate) fn record(&self, access: &Access) {
+        let mut writer = self.writer.lock().unwrap();
+        // NOTE: A failing trace shouldn't fail the read it's logging, so write errors are dropped
+        let _ = writeln!(
+            writer,
+            "{:.6},\"{}\",{},{},{},{},{}",
+            self.start.elapsed().as_secs_f64(),
+            access.path.replace('"', "\"\""),
+            access.method,
+            access.size_packed,
+            access.size_unpacked,
+            access.bytes_read,
+            access.duration.as_micros()
+        );
+    }
+}